@@ -0,0 +1,213 @@
+use std::fmt;
+
+
+/// How decision values are turned into probabilities of class 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Calibration {
+    None,
+    /// Platt scaling: a sigmoid fitted to the decision values.
+    Platt,
+    /// Isotonic regression: a non-decreasing step function fitted to the decision values.
+    Isotonic
+}
+
+impl Calibration {
+    pub fn parse(name: &str) -> Result<Calibration, String> {
+        match name {
+            "none" => Ok(Calibration::None),
+            "platt" => Ok(Calibration::Platt),
+            "isotonic" => Ok(Calibration::Isotonic),
+            other => Err(format!("Unknown calibration `{}` (expected none, platt or isotonic)", other))
+        }
+    }
+}
+
+/// The name accepted by `Calibration::parse`.
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Calibration::None => "none",
+            Calibration::Platt => "platt",
+            Calibration::Isotonic => "isotonic"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+/// A fitted map from decision values to probabilities. It should be fitted on
+/// decision values for samples the model wasn't trained on (e.g. out-of-fold scores
+/// from cross-validation), since training samples score over-confidently.
+#[derive(Debug, Clone)]
+pub enum Calibrator {
+    /// `P = 1 / (1 + exp(a * score + b))`
+    Platt { a: f64, b: f64 },
+    /// `probabilities[i]` applies from `edges[i]` up to the next edge; scores below the
+    /// first edge get the first probability.
+    Isotonic { edges: Vec<f64>, probabilities: Vec<f64> }
+}
+
+impl Calibrator {
+    /// `None` for `Calibration::None`.
+    pub fn fit(method: Calibration, scores: &[f64], labels: &[bool]) -> Option<Calibrator> {
+        assert_eq!(scores.len(), labels.len());
+        match method {
+            Calibration::None => None,
+            Calibration::Platt => Some(platt(scores, labels)),
+            Calibration::Isotonic => Some(isotonic(scores, labels))
+        }
+    }
+
+    pub fn probability(&self, score: f64) -> f64 {
+        match self {
+            Calibrator::Platt { a, b } => sigmoid(a * score + b),
+            Calibrator::Isotonic { edges, probabilities } => {
+                let i = edges.iter().take_while(|e| **e <= score).count();
+                probabilities[i.max(1) - 1]
+            }
+        }
+    }
+}
+
+
+/// `1 / (1 + exp(z))`, written to avoid overflow for large |z|.
+fn sigmoid(z: f64) -> f64 {
+    if z >= 0.0 {
+        (-z).exp() / (1.0 + (-z).exp())
+    } else {
+        1.0 / (1.0 + z.exp())
+    }
+}
+
+/// Platt's method with the regularised targets and Newton iterations of Lin, Lin &
+/// Weng (2007), "A note on Platt's probabilistic outputs for support vector machines".
+fn platt(scores: &[f64], labels: &[bool]) -> Calibrator {
+    let positives = labels.iter().filter(|l| **l).count() as f64;
+    let negatives = labels.len() as f64 - positives;
+    let high = (positives + 1.0) / (positives + 2.0);
+    let low = 1.0 / (negatives + 2.0);
+    let targets: Vec<f64> = labels.iter().map(|l| if *l { high } else { low }).collect();
+
+    let objective = |a: f64, b: f64| -> f64 {
+        scores.iter().zip(&targets).map(|(f, t)| {
+            let z = f * a + b;
+            // cross-entropy of sigmoid(z) against t, in a form that doesn't overflow
+            if z >= 0.0 {
+                t * z + (1.0 + (-z).exp()).ln()
+            } else {
+                (t - 1.0) * z + (1.0 + z.exp()).ln()
+            }
+        }).sum()
+    };
+
+    let (mut a, mut b) = (0.0, ((negatives + 1.0) / (positives + 1.0)).ln());
+    let mut value = objective(a, b);
+    let sigma = 1e-12;
+    for _iteration in 0..100 {
+        // gradient and Hessian (with a small ridge so it stays invertible)
+        let (mut h11, mut h22, mut h21, mut g1, mut g2) = (sigma, sigma, 0.0, 0.0, 0.0);
+        for (f, t) in scores.iter().zip(&targets) {
+            let p = sigmoid(f * a + b);
+            let q = 1.0 - p;
+            let d2 = p * q;
+            h11 += f * f * d2;
+            h22 += d2;
+            h21 += f * d2;
+            let d1 = t - p;
+            g1 += f * d1;
+            g2 += d1;
+        }
+        if g1.abs() < 1e-5 && g2.abs() < 1e-5 {
+            break;
+        }
+
+        let det = h11 * h22 - h21 * h21;
+        let da = -(h22 * g1 - h21 * g2) / det;
+        let db = -(-h21 * g1 + h11 * g2) / det;
+        let gd = g1 * da + g2 * db;
+
+        // backtracking line search
+        let mut step = 1.0;
+        while step >= 1e-10 {
+            let (new_a, new_b) = (a + step * da, b + step * db);
+            let new_value = objective(new_a, new_b);
+            if new_value < value + 1e-4 * step * gd {
+                a = new_a;
+                b = new_b;
+                value = new_value;
+                break;
+            }
+            step /= 2.0;
+        }
+        if step < 1e-10 {
+            break;
+        }
+    }
+
+    Calibrator::Platt { a, b }
+}
+
+/// Pool-adjacent-violators: sort by score, then merge neighbouring blocks until the
+/// fraction of positives never decreases.
+fn isotonic(scores: &[f64], labels: &[bool]) -> Calibrator {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|i, j| scores[*i].partial_cmp(&scores[*j]).unwrap());
+
+    // (lowest score, positives, count) for each block
+    let mut blocks: Vec<(f64, f64, f64)> = Vec::new();
+    for i in order {
+        blocks.push((scores[i], labels[i] as u8 as f64, 1.0));
+        while blocks.len() > 1 {
+            let (_, p2, n2) = blocks[blocks.len() - 1];
+            let (e1, p1, n1) = blocks[blocks.len() - 2];
+            if p1 / n1 < p2 / n2 {
+                break;
+            }
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (e1, p1 + p2, n1 + n2);
+        }
+    }
+
+    Calibrator::Isotonic {
+        edges: blocks.iter().map(|(e, _, _)| *e).collect(),
+        probabilities: blocks.iter().map(|(_, p, n)| p / n).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isotonic_pools_adjacent_violators_into_a_non_decreasing_step() {
+        let labels = [false, true, false, true, true];
+        let calibrator = Calibrator::fit(Calibration::Isotonic, &[1.0, 2.0, 3.0, 4.0, 5.0], &labels).unwrap();
+        match &calibrator {
+            Calibrator::Isotonic { edges, probabilities } => {
+                assert_eq!(edges, &vec![1.0, 2.0, 4.0]);
+                assert_eq!(probabilities, &vec![0.0, 0.5, 1.0]);
+            },
+            _ => panic!("expected an isotonic calibrator")
+        }
+        let probabilities: Vec<f64> = [0.0, 1.0, 2.5, 3.9, 4.0, 10.0].iter().map(|s| calibrator.probability(*s)).collect();
+        assert_eq!(probabilities, vec![0.0, 0.0, 0.5, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn platt_fits_a_decreasing_sigmoid_of_the_score() {
+        let scores = [-2.0, -1.5, -1.0, -0.5, 0.5, 1.0, 1.5, 2.0, -0.2, 0.2];
+        let labels = [false, false, false, false, true, true, true, true, true, false];
+        let calibrator = Calibrator::fit(Calibration::Platt, &scores, &labels).unwrap();
+        match calibrator {
+            Calibrator::Platt { a, .. } => assert!(a < 0.0),
+            _ => panic!("expected a Platt calibrator")
+        }
+        let probabilities: Vec<f64> = [-3.0, -1.0, 0.0, 1.0, 3.0].iter().map(|s| calibrator.probability(*s)).collect();
+        assert!(probabilities.windows(2).all(|w| w[0] < w[1]));
+        assert!(probabilities[0] < 0.1 && probabilities[4] > 0.9);
+        // the data are symmetric about zero
+        assert!((probabilities[2] - 0.5).abs() < 1e-6);
+        assert!(Calibrator::fit(Calibration::None, &scores, &labels).is_none());
+    }
+}
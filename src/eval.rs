@@ -0,0 +1,114 @@
+use std::fmt;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+
+/// Counts of actual (rows) against predicted (columns) classes, where classes are
+/// indices into `labels`.
+#[derive(Debug, Clone)]
+pub struct ConfusionMatrix {
+    pub labels: Vec<String>,
+    pub counts: Vec<Vec<usize>>
+}
+
+impl ConfusionMatrix {
+    pub fn new(labels: &[&str]) -> ConfusionMatrix {
+        ConfusionMatrix {
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            counts: vec![vec![0; labels.len()]; labels.len()]
+        }
+    }
+
+    pub fn add(&mut self, actual: usize, predicted: usize) {
+        self.counts[actual][predicted] += 1;
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().flatten().sum()
+    }
+
+    pub fn correct(&self) -> usize {
+        (0..self.labels.len()).map(|c| self.counts[c][c]).sum()
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        self.correct() as f64 / self.total() as f64
+    }
+}
+
+impl fmt::Display for ConfusionMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let corner = "actual \\ predicted";
+        let width = self.labels.iter().map(|l| l.len()).max().unwrap_or(0).max(6);
+        let first = width.max(corner.len());
+        write!(f, "{:>w$}", corner, w = first)?;
+        for label in &self.labels {
+            write!(f, " {:>w$}", label, w = width)?;
+        }
+        writeln!(f)?;
+        for (label, row) in self.labels.iter().zip(&self.counts) {
+            write!(f, "{:>w$}", label, w = first)?;
+            for count in row {
+                write!(f, " {:>w$}", count, w = width)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "Success rate: {}/{} ({:.1}%)", self.correct(), self.total(), 100.0 * self.accuracy())
+    }
+}
+
+
+/// Splits `0..classes.len()` into `k` folds for cross-validation, dealing each class out
+/// in turn after shuffling so every fold gets a similar class balance. Folds may be
+/// empty if there are fewer samples than folds.
+pub fn stratified_folds(classes: &[i8], k: usize, rng: &mut StdRng) -> Vec<Vec<usize>> {
+    let mut distinct = classes.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+
+    let mut folds = vec![Vec::new(); k];
+    let mut next = 0;
+    for class in distinct {
+        let mut members: Vec<usize> = (0..classes.len()).filter(|i| classes[*i] == class).collect();
+        members.shuffle(rng);
+        for i in members {
+            folds[next % k].push(i);
+            next += 1;
+        }
+    }
+    folds
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn confusion_matrix_counts_the_diagonal_as_correct() {
+        let mut matrix = ConfusionMatrix::new(&["a", "b", "c"]);
+        for (actual, predicted) in &[(0, 0), (0, 1), (1, 1), (2, 2), (2, 2), (2, 0)] {
+            matrix.add(*actual, *predicted);
+        }
+        assert_eq!(matrix.counts, vec![vec![1, 1, 0], vec![0, 1, 0], vec![1, 0, 2]]);
+        assert_eq!((matrix.correct(), matrix.total()), (4, 6));
+        assert!((matrix.accuracy() - 4.0 / 6.0).abs() < 1e-12);
+        assert!(matrix.to_string().ends_with("Success rate: 4/6 (66.7%)"));
+    }
+
+    #[test]
+    fn stratified_folds_partition_the_samples_and_balance_the_classes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let classes = [0, 0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 2];
+        let folds = stratified_folds(&classes, 3, &mut rng);
+        let mut all: Vec<usize> = folds.iter().flatten().cloned().collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..classes.len()).collect::<Vec<usize>>());
+        for fold in &folds {
+            let count = |c: i8| fold.iter().filter(|i| classes[**i] == c).count();
+            assert_eq!((count(0), count(1), count(2)), (2, 1, 1));
+        }
+        assert!(stratified_folds(&classes, 20, &mut rng).iter().any(|f| f.is_empty()));
+    }
+}
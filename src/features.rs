@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use crate::CorpusStats;
+use crate::histogram::{Histogram, SUMMARY_NAMES};
+use crate::perceptron::Sample;
+use crate::scaling::{Scaler, Scaling};
+use crate::selection::{Selection, Selector};
+
+
+/// Scalar feature names. Most are ratios so that books of different lengths are
+/// comparable; the raw counts are there for completeness.
+pub const SCALARS: [&str; 17] = [
+    "bias",
+    "comma_rate",
+    "hapax_ratio",
+    "dis_ratio",
+    "type_token_ratio",
+    "italic_rate",
+    "capitalised_rate",
+    "syllables_per_word",
+    "polysyllable_rate",
+    "flesch_reading_ease",
+    "flesch_kincaid_grade",
+    "total_sentences",
+    "total_commas",
+    "total_words",
+    "hapax_legomena",
+    "dis_legomena",
+    "unique_words"
+];
+
+/// Distribution features. Each expands to one feature per histogram bin, and
+/// `<name>.<summary>` (e.g. `sentence_length_dist.median`) picks out a single summary statistic.
+pub const DISTRIBUTIONS: [&str; 5] = [
+    "sentence_length_dist",
+    "word_length_dist",
+    "pronouns_per_sentence_dist",
+    "conjunctions_per_sentence_dist",
+    "syllables_per_word_dist"
+];
+
+/// Frequency features over a vocabulary fitted on the training books: relative word
+/// frequencies (`word:the`), character trigram frequencies (`char:_th`), and raw
+/// word counts (`count:the`).
+pub const NGRAMS: [&str; 3] = ["words", "char_ngrams", "word_counts"];
+
+/// The ratios the corpus perceptron has always used.
+pub const DEFAULT: [&str; 7] = ["bias", "comma_rate", "hapax_ratio", "dis_ratio", "type_token_ratio", "italic_rate", "capitalised_rate"];
+
+
+fn scalar(stats: &CorpusStats, name: &str) -> Option<f64> {
+    let value = match name {
+        "bias" => 1.0,
+        "comma_rate" => stats.total_commas as f64 / stats.total_sentences as f64,
+        "hapax_ratio" => stats.hapax_legomena as f64 / stats.total_words as f64,
+        "dis_ratio" => stats.dis_legomena as f64 / stats.total_words as f64,
+        "type_token_ratio" => stats.unique_words as f64 / stats.total_words as f64,
+        "italic_rate" => stats.italic_rate,
+        "capitalised_rate" => stats.capitalised_rate,
+        "syllables_per_word" => stats.syllables_per_word,
+        "polysyllable_rate" => stats.polysyllable_rate,
+        "flesch_reading_ease" => stats.flesch_reading_ease,
+        "flesch_kincaid_grade" => stats.flesch_kincaid_grade,
+        "total_sentences" => stats.total_sentences as f64,
+        "total_commas" => stats.total_commas as f64,
+        "total_words" => stats.total_words as f64,
+        "hapax_legomena" => stats.hapax_legomena as f64,
+        "dis_legomena" => stats.dis_legomena as f64,
+        "unique_words" => stats.unique_words as f64,
+        _ => return None
+    };
+    Some(value)
+}
+
+fn distribution<'a>(stats: &'a CorpusStats, name: &str) -> Option<&'a Histogram> {
+    match name {
+        "sentence_length_dist" => Some(&stats.sentence_length_dist),
+        "word_length_dist" => Some(&stats.word_length_dist),
+        "pronouns_per_sentence_dist" => Some(&stats.pronouns_per_sentence_dist),
+        "conjunctions_per_sentence_dist" => Some(&stats.conjunctions_per_sentence_dist),
+        "syllables_per_word_dist" => Some(&stats.syllables_per_word_dist),
+        _ => None
+    }
+}
+
+
+fn ngram_frequencies<'a>(stats: &'a CorpusStats, name: &str) -> Option<(&'static str, &'a HashMap<String, f64>)> {
+    match name {
+        "words" => Some(("word", &stats.word_frequencies)),
+        "char_ngrams" => Some(("char", &stats.char_ngram_frequencies)),
+        "word_counts" => Some(("count", &stats.word_counts)),
+        _ => None
+    }
+}
+
+
+/// An ordered list of `CorpusStats` fields to turn into feature vectors. Every book
+/// assembled with the same set (and the same `Binning`) gets the same named columns
+/// in the same order, whichever model the vectors end up in.
+///
+/// The n-gram fields only produce columns once `fit` has fixed their vocabularies.
+#[derive(Debug, Clone)]
+pub struct FeatureSet {
+    pub fields: Vec<String>,
+    pub vocabularies: HashMap<String, Vec<String>>
+}
+
+impl FeatureSet {
+    pub fn new(fields: &[&str]) -> Result<FeatureSet, String> {
+        for field in fields {
+            if !FeatureSet::is_known(field) {
+                return Err(format!("Unknown feature `{}`. Scalars: {}. Distributions: {} (or `<distribution>.<{}>`). N-grams: {}.",
+                                   field, SCALARS.join(", "), DISTRIBUTIONS.join(", "), SUMMARY_NAMES.join("|"), NGRAMS.join(", ")));
+            }
+        }
+        Ok(FeatureSet { fields: fields.iter().map(|f| f.to_string()).collect(), vocabularies: HashMap::new() })
+    }
+
+    /// Fixes the n-gram vocabularies to everything seen in the training books, most
+    /// frequent first.
+    pub fn fit<'a, I: IntoIterator<Item = &'a CorpusStats>>(&self, training: I) -> FeatureSet {
+        let training: Vec<&CorpusStats> = training.into_iter().collect();
+        let mut vocabularies = HashMap::new();
+        for field in self.fields.iter().filter(|f| NGRAMS.contains(&f.as_str())) {
+            let mut totals: HashMap<&str, f64> = HashMap::new();
+            for book in &training {
+                let (_, frequencies) = ngram_frequencies(book, field).unwrap();
+                for (gram, frequency) in frequencies {
+                    *totals.entry(gram).or_insert(0.0) += frequency;
+                }
+            }
+            let mut vocabulary: Vec<(&str, f64)> = totals.into_iter().collect();
+            vocabulary.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(b.0)));
+            vocabularies.insert(field.clone(), vocabulary.into_iter().map(|(gram, _)| gram.to_string()).collect());
+        }
+        FeatureSet { fields: self.fields.clone(), vocabularies }
+    }
+
+    /// Parses a comma-separated list of field names, e.g. from the command line.
+    pub fn parse(list: &str) -> Result<FeatureSet, String> {
+        let fields: Vec<&str> = list.split(',').map(|f| f.trim()).filter(|f| !f.is_empty()).collect();
+        FeatureSet::new(&fields)
+    }
+
+    fn is_known(field: &str) -> bool {
+        if SCALARS.contains(&field) || DISTRIBUTIONS.contains(&field) || NGRAMS.contains(&field) {
+            return true;
+        }
+        match field.split_once('.') {
+            Some((dist, summary)) => DISTRIBUTIONS.contains(&dist) && SUMMARY_NAMES.contains(&summary),
+            None => false
+        }
+    }
+
+    /// The named features for one book, in set order.
+    pub fn assemble(&self, stats: &CorpusStats) -> Vec<(String, f64)> {
+        let mut features = Vec::new();
+        for field in &self.fields {
+            if let Some(value) = scalar(stats, field) {
+                features.push((field.clone(), value));
+            } else if let Some(hist) = distribution(stats, field) {
+                let edges = hist.edges();
+                for (i, fraction) in hist.fractions().iter().enumerate() {
+                    features.push((format!("{}[{},{})", field, edges[i], edges[i+1]), *fraction));
+                }
+            } else if let Some((prefix, frequencies)) = ngram_frequencies(stats, field) {
+                if let Some(vocabulary) = self.vocabularies.get(field) {
+                    for gram in vocabulary {
+                        features.push((format!("{}:{}", prefix, gram), *frequencies.get(gram).unwrap_or(&0.0)));
+                    }
+                }
+            } else if let Some((dist, summary)) = field.split_once('.') {
+                let hist = distribution(stats, dist).unwrap();
+                let i = SUMMARY_NAMES.iter().position(|s| *s == summary).unwrap();
+                features.push((field.clone(), hist.summary()[i]));
+            }
+        }
+        features
+    }
+
+    pub fn names(&self, stats: &CorpusStats) -> Vec<String> {
+        self.assemble(stats).into_iter().map(|(name, _)| name).collect()
+    }
+
+    pub fn vector(&self, stats: &CorpusStats) -> Vec<f64> {
+        self.assemble(stats).into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// One `Sample` per book, labelled with the book's class.
+    pub fn samples<'a, I: IntoIterator<Item = &'a CorpusStats>>(&self, books: I) -> Vec<Sample> {
+        books.into_iter().map(|b| Sample { values: self.vector(b), class: b.class as i8 }).collect()
+    }
+}
+
+
+/// Everything needed to turn a book into the vector a model was trained on: the
+/// feature set with its vocabularies, the selected features, and the scaling, all
+/// fitted to the training books. Keep it with the model and push test and unknown
+/// books through the same pipeline.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub features: FeatureSet,
+    pub selector: Selector,
+    pub scaler: Scaler,
+    /// Names of the final (selected) features
+    pub names: Vec<String>
+}
+
+impl Pipeline {
+    pub fn fit<'a, I: IntoIterator<Item = &'a CorpusStats>>(features: &FeatureSet, selection: Selection, scaling: Scaling, training: I) -> Result<Pipeline, String> {
+        let training: Vec<&CorpusStats> = training.into_iter().collect();
+        if training.is_empty() {
+            return Err("No training books to fit the features to".to_string());
+        }
+        let features = features.fit(training.iter().cloned());
+        let all_names = features.names(training[0]);
+        let raw = features.samples(training);
+
+        // selection looks at raw frequencies, so it comes before scaling
+        let selector = Selector::fit(selection, &all_names, &raw);
+        let names = selector.select_names(&all_names);
+        let selected = selector.apply_all(&raw);
+        let scaler = Scaler::fit(scaling, &names, &selected);
+        Ok(Pipeline { features, selector, scaler, names })
+    }
+
+    pub fn samples<'a, I: IntoIterator<Item = &'a CorpusStats>>(&self, books: I) -> Vec<Sample> {
+        self.scaler.transform_all(&self.selector.apply_all(&self.features.samples(books)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_pipeline_needs_training_books() {
+        let features = FeatureSet::new(&["bias"]).unwrap();
+        assert!(Pipeline::fit(&features, Selection::All, Scaling::None, std::iter::empty()).is_err());
+    }
+}
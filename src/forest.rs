@@ -0,0 +1,291 @@
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::{index, SliceRandom};
+use crate::perceptron::Sample;
+
+
+/// Shuffles of each feature averaged over for its permutation importance.
+const PERMUTATIONS: usize = 10;
+
+
+/// A CART decision tree node. Samples whose `feature` is at most `threshold` go left.
+#[derive(Debug, Clone)]
+pub enum Node {
+    /// The majority class of the training samples that reached the leaf, and the
+    /// fraction of them in it.
+    Leaf { class: i8, share: f64 },
+    Split { feature: usize, threshold: f64, left: Box<Node>, right: Box<Node> }
+}
+
+impl Node {
+    pub fn leaf(&self, values: &[f64]) -> (i8, f64) {
+        match self {
+            Node::Leaf { class, share } => (*class, *share),
+            Node::Split { feature, threshold, left, right } => {
+                if values[*feature] <= *threshold { left.leaf(values) } else { right.leaf(values) }
+            }
+        }
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        self.leaf(values).0
+    }
+
+    /// The leaf's share, signed by whether its class is 1.
+    pub fn score(&self, values: &[f64]) -> f64 {
+        let (class, share) = self.leaf(values);
+        if class == 1 { share } else { -share }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            Node::Leaf { .. } => 0,
+            Node::Split { left, right, .. } => 1 + left.depth().max(right.depth())
+        }
+    }
+
+    pub fn leaves(&self) -> usize {
+        match self {
+            Node::Leaf { .. } => 1,
+            Node::Split { left, right, .. } => left.leaves() + right.leaves()
+        }
+    }
+}
+
+
+/// Grows a tree by recursive binary splits, each the one with the lowest weighted Gini
+/// impurity among `tried` features drawn at random (all of them for a plain CART
+/// tree), until a node is pure, can't be split, or is `max_depth` deep. `rows` index
+/// into `samples`, and may repeat for a bootstrap sample.
+pub fn grow(samples: &[Sample], rows: &[usize], max_depth: usize, tried: usize, rng: &mut StdRng) -> Result<Node, String> {
+    check_growable(samples)?;
+    let mut classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+    classes.sort_unstable();
+    classes.dedup();
+    Ok(split(samples, &classes, rows.to_vec(), max_depth, tried, rng))
+}
+
+fn check_growable(samples: &[Sample]) -> Result<(), String> {
+    if samples.is_empty() || samples[0].values.is_empty() {
+        return Err("Trees need at least one sample and one feature".to_string());
+    }
+    Ok(())
+}
+
+fn split(samples: &[Sample], classes: &[i8], rows: Vec<usize>, depth_left: usize, tried: usize, rng: &mut StdRng) -> Node {
+    let counts = class_counts(samples, classes, &rows);
+    let (majority, most) = counts.iter().enumerate().max_by_key(|(_, n)| **n).map(|(c, n)| (classes[c], *n)).unwrap();
+    let leaf = Node::Leaf { class: majority, share: most as f64 / rows.len() as f64 };
+    if depth_left == 0 || most == rows.len() {
+        return leaf;
+    }
+
+    let dims = samples[0].values.len();
+    let mut best: Option<(f64, usize, f64)> = None;
+    for feature in index::sample(rng, dims, tried.clamp(1, dims)).iter() {
+        let mut sorted = rows.clone();
+        sorted.sort_by(|a, b| samples[*a].values[feature].partial_cmp(&samples[*b].values[feature]).unwrap());
+
+        let mut left = vec![0; classes.len()];
+        let mut right = counts.clone();
+        for i in 0..sorted.len() - 1 {
+            let c = classes.iter().position(|c| *c == samples[sorted[i]].class).unwrap();
+            left[c] += 1;
+            right[c] -= 1;
+            let (here, next) = (samples[sorted[i]].values[feature], samples[sorted[i + 1]].values[feature]);
+            if here == next {
+                continue;
+            }
+            let impurity = (i + 1) as f64 * gini(&left) + (sorted.len() - i - 1) as f64 * gini(&right);
+            let better = match best {
+                Some((b, _, _)) => impurity < b,
+                None => true
+            };
+            if better {
+                best = Some((impurity, feature, (here + next) / 2.0));
+            }
+        }
+    }
+
+    match best {
+        // a split has to do better than the node as it is
+        Some((impurity, feature, threshold)) if impurity < rows.len() as f64 * gini(&counts) => {
+            let (left, right): (Vec<usize>, Vec<usize>) = rows.iter().partition(|r| samples[**r].values[feature] <= threshold);
+            Node::Split {
+                feature,
+                threshold,
+                left: Box::new(split(samples, classes, left, depth_left - 1, tried, rng)),
+                right: Box::new(split(samples, classes, right, depth_left - 1, tried, rng))
+            }
+        },
+        _ => leaf
+    }
+}
+
+fn class_counts(samples: &[Sample], classes: &[i8], rows: &[usize]) -> Vec<usize> {
+    let mut counts = vec![0; classes.len()];
+    for r in rows {
+        counts[classes.iter().position(|c| *c == samples[*r].class).unwrap()] += 1;
+    }
+    counts
+}
+
+fn gini(counts: &[usize]) -> f64 {
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    1.0 - counts.iter().map(|n| (*n as f64 / total as f64).powi(2)).sum::<f64>()
+}
+
+
+/// Breiman's random forest: trees grown on bootstrap samples, each split choosing among
+/// the square root of the number of features, voting on the class.
+#[derive(Debug, Clone)]
+pub struct Forest {
+    pub trees: Vec<Node>
+}
+
+impl Forest {
+    /// Also returns, per tree, which samples were in its bootstrap sample, for the
+    /// out-of-bag estimates.
+    pub fn fit(samples: &[Sample], trees: usize, max_depth: usize, rng: &mut StdRng) -> Result<(Forest, Vec<Vec<bool>>), String> {
+        check_growable(samples)?;
+        let n = samples.len();
+        let tried = (samples[0].values.len() as f64).sqrt().round() as usize;
+        let mut grown = Vec::new();
+        let mut in_bag = Vec::new();
+        for _tree in 0..trees {
+            let rows: Vec<usize> = (0..n).map(|_| rng.gen_range(0..n)).collect();
+            let mut bag = vec![false; n];
+            for r in &rows {
+                bag[*r] = true;
+            }
+            grown.push(grow(samples, &rows, max_depth, tried, rng)?);
+            in_bag.push(bag);
+        }
+        Ok((Forest { trees: grown }, in_bag))
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        majority(self.trees.iter().map(|t| t.predict(values)))
+    }
+
+    /// Votes for class 1 less votes against, as a fraction of the trees: positive means class 1.
+    pub fn score(&self, values: &[f64]) -> f64 {
+        let ones = self.trees.iter().filter(|t| t.predict(values) == 1).count() as f64;
+        (2.0 * ones - self.trees.len() as f64) / self.trees.len() as f64
+    }
+
+    /// Accuracy on the training samples, each voted on only by the trees that didn't
+    /// see it; samples every tree saw are left out.
+    pub fn out_of_bag_accuracy(&self, samples: &[Sample], in_bag: &[Vec<bool>]) -> f64 {
+        let mut correct = 0;
+        let mut voted = 0;
+        for (i, s) in samples.iter().enumerate() {
+            let votes: Vec<i8> = self.trees.iter().zip(in_bag).filter(|(_, bag)| !bag[i]).map(|(t, _)| t.predict(&s.values)).collect();
+            if !votes.is_empty() {
+                voted += 1;
+                if majority(votes.into_iter()) == s.class {
+                    correct += 1;
+                }
+            }
+        }
+        correct as f64 / voted.max(1) as f64
+    }
+
+    /// Permutation importance of each feature: how far the out-of-bag accuracy falls
+    /// when that feature's values are shuffled among the samples, which breaks its link
+    /// to the class while keeping its distribution.
+    pub fn importances(&self, samples: &[Sample], in_bag: &[Vec<bool>], rng: &mut StdRng) -> Vec<f64> {
+        let baseline = self.out_of_bag_accuracy(samples, in_bag);
+        (0..samples[0].values.len()).map(|j| {
+            let mut drop = 0.0;
+            for _permutation in 0..PERMUTATIONS {
+                let mut column: Vec<f64> = samples.iter().map(|s| s.values[j]).collect();
+                column.shuffle(rng);
+                let permuted: Vec<Sample> = samples.iter().zip(column)
+                    .map(|(s, x)| {
+                        let mut values = s.values.clone();
+                        values[j] = x;
+                        Sample { values, class: s.class }
+                    })
+                    .collect();
+                drop += baseline - self.out_of_bag_accuracy(&permuted, in_bag);
+            }
+            drop / PERMUTATIONS as f64
+        }).collect()
+    }
+}
+
+
+/// The most common class, ties going to the one seen first.
+fn majority(votes: impl Iterator<Item = i8>) -> i8 {
+    let mut counts: Vec<(i8, usize)> = Vec::new();
+    for v in votes {
+        match counts.iter_mut().find(|(c, _)| *c == v) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((v, 1))
+        }
+    }
+    let mut best = counts[0];
+    for c in &counts {
+        if c.1 > best.1 {
+            best = *c;
+        }
+    }
+    best.0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+
+    /// Class 1 exactly when the first value is above 5; the second is noise.
+    fn samples(rng: &mut StdRng) -> Vec<Sample> {
+        (0..40).map(|i| {
+            let x = i as f64 / 4.0;
+            Sample { values: vec![x, rng.gen_range(0.0..10.0)], class: (x > 5.0) as i8 }
+        }).collect()
+    }
+
+    #[test]
+    fn a_tree_splits_once_on_the_separating_feature() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let samples = samples(&mut rng);
+        let rows: Vec<usize> = (0..samples.len()).collect();
+        let tree = grow(&samples, &rows, 10, 2, &mut rng).unwrap();
+        match &tree {
+            Node::Split { feature, threshold, .. } => assert_eq!((*feature, *threshold), (0, 5.125)),
+            leaf => panic!("expected a split, got {:?}", leaf)
+        }
+        assert_eq!((tree.depth(), tree.leaves()), (1, 2));
+        assert_eq!(tree.leaf(&[9.0, 0.0]), (1, 1.0));
+
+        let stump = grow(&samples, &rows, 0, 2, &mut rng).unwrap();
+        assert_eq!(stump.leaf(&[9.0, 0.0]), (0, 0.525));
+    }
+
+    #[test]
+    fn the_forest_finds_the_important_feature_out_of_bag() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let samples = samples(&mut rng);
+        let (forest, in_bag) = Forest::fit(&samples, 25, 10, &mut rng).unwrap();
+        assert_eq!((forest.trees.len(), in_bag.len()), (25, 25));
+        assert!(forest.out_of_bag_accuracy(&samples, &in_bag) > 0.9);
+        let importances = forest.importances(&samples, &in_bag, &mut rng);
+        assert!(importances[0] > importances[1]);
+        assert!(forest.score(&[9.0, 5.0]) > 0.0 && forest.score(&[1.0, 5.0]) < 0.0);
+    }
+
+    #[test]
+    fn growing_needs_samples_and_features() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let featureless = vec![Sample { values: Vec::new(), class: 0 }, Sample { values: Vec::new(), class: 1 }];
+        assert!(grow(&featureless, &[0, 1], 10, 1, &mut rng).is_err());
+        assert!(Forest::fit(&featureless, 5, 10, &mut rng).is_err());
+        assert!(Forest::fit(&[], 5, 10, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+
+
+/// Names of the values returned by `Histogram::summary`, in order.
+pub const SUMMARY_NAMES: [&str; 10] = ["mean", "variance", "skew", "p10", "p25", "median", "p75", "p90", "underflow", "overflow"];
+
+
+/// A weighted histogram over arbitrary bin edges. Bin `i` covers `[edges[i], edges[i+1])`;
+/// observations outside the edges are kept in separate underflow and overflow counts
+/// rather than silently folded into the end bins. Moments are accumulated from the raw
+/// observations, so they are exact regardless of the binning.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<f64>,
+    underflow: f64,
+    overflow: f64,
+    weight: f64,
+    sum: f64,
+    sum_sq: f64,
+    sum_cube: f64
+}
+
+impl Histogram {
+    pub fn new(edges: &[f64]) -> Histogram {
+        assert!(edges.len() >= 2, "A histogram needs at least two bin edges");
+        assert!(edges.windows(2).all(|w| w[0] < w[1]), "Histogram bin edges must be strictly increasing");
+        Histogram {
+            edges: edges.to_vec(),
+            counts: vec![0.0; edges.len() - 1],
+            underflow: 0.0,
+            overflow: 0.0,
+            weight: 0.0,
+            sum: 0.0,
+            sum_sq: 0.0,
+            sum_cube: 0.0
+        }
+    }
+
+    /// Edges for `bins` bins of equal `width`, starting at `start`.
+    pub fn uniform_edges(start: f64, width: f64, bins: usize) -> Vec<f64> {
+        (0..=bins).map(|i| start + width * i as f64).collect()
+    }
+
+    pub fn edges(&self) -> &[f64] {
+        &self.edges
+    }
+
+    pub fn add(&mut self, x: f64) {
+        self.add_weighted(x, 1.0);
+    }
+
+    pub fn add_weighted(&mut self, x: f64, w: f64) {
+        self.weight += w;
+        self.sum += w * x;
+        self.sum_sq += w * x * x;
+        self.sum_cube += w * x * x * x;
+
+        if x < self.edges[0] {
+            self.underflow += w;
+        } else if x >= self.edges[self.edges.len() - 1] {
+            self.overflow += w;
+        } else {
+            // edges are sorted, so the bin is one less than the first edge above x
+            let bin = self.edges.partition_point(|e| *e <= x) - 1;
+            self.counts[bin] += w;
+        }
+    }
+
+    /// Fraction of the total weight falling in each bin. Under- and overflow count
+    /// towards the total, so these only sum to one when nothing fell outside the edges.
+    pub fn fractions(&self) -> Vec<f64> {
+        self.counts.iter().map(|c| self.fraction(*c)).collect()
+    }
+
+    pub fn underflow(&self) -> f64 {
+        self.fraction(self.underflow)
+    }
+
+    pub fn overflow(&self) -> f64 {
+        self.fraction(self.overflow)
+    }
+
+    fn fraction(&self, c: f64) -> f64 {
+        if self.weight > 0.0 {
+            c / self.weight
+        } else {
+            0.0
+        }
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.fraction(self.sum)
+    }
+
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+        (self.fraction(self.sum_sq) - mean * mean).max(0.0)
+    }
+
+    pub fn skew(&self) -> f64 {
+        let mean = self.mean();
+        let sd = self.variance().sqrt();
+        if sd == 0.0 {
+            return 0.0;
+        }
+        let third_moment = self.fraction(self.sum_cube) - 3.0 * mean * self.fraction(self.sum_sq) + 2.0 * mean.powi(3);
+        third_moment / sd.powi(3)
+    }
+
+    /// The `p`th percentile (0 to 1), interpolated linearly within bins. Weight outside
+    /// the edges is treated as sitting on the outermost edge.
+    pub fn percentile(&self, p: f64) -> f64 {
+        let target = p.clamp(0.0, 1.0) * self.weight;
+        let mut cumulative = self.underflow;
+        if target <= cumulative {
+            return self.edges[0];
+        }
+        for (i, c) in self.counts.iter().enumerate() {
+            if *c > 0.0 && cumulative + c >= target {
+                let within = (target - cumulative) / c;
+                return self.edges[i] + within * (self.edges[i+1] - self.edges[i]);
+            }
+            cumulative += c;
+        }
+        self.edges[self.edges.len() - 1]
+    }
+
+    /// Summary statistics in the order given by `SUMMARY_NAMES`.
+    pub fn summary(&self) -> Vec<f64> {
+        vec![
+            self.mean(),
+            self.variance(),
+            self.skew(),
+            self.percentile(0.10),
+            self.percentile(0.25),
+            self.percentile(0.50),
+            self.percentile(0.75),
+            self.percentile(0.90),
+            self.underflow(),
+            self.overflow()
+        ]
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for l in self.fractions() {
+            write!(f, "  {:.2}", l)?;
+        }
+        write!(f, "  |")?;
+        for (name, value) in SUMMARY_NAMES.iter().zip(self.summary()) {
+            write!(f, "  {}: {:.2}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_outside_the_edges_go_to_underflow_and_overflow() {
+        let mut h = Histogram::new(&[0.0, 1.0, 2.0]);
+        for x in [-1.0, 0.0, 0.5, 1.0, 2.0] {
+            h.add(x);
+        }
+        assert_eq!(h.fractions(), vec![0.4, 0.2]);
+        assert_eq!(h.underflow(), 0.2);
+        assert_eq!(h.overflow(), 0.2);
+    }
+
+    #[test]
+    fn percentiles_interpolate_within_bins() {
+        let mut h = Histogram::new(&Histogram::uniform_edges(0.0, 10.0, 4));
+        h.add_weighted(5.0, 1.0);
+        h.add_weighted(15.0, 3.0);
+        // a quarter of the weight is in [0, 10) and the rest in [10, 20)
+        assert_eq!(h.percentile(0.0), 0.0);
+        assert_eq!(h.percentile(0.125), 5.0);
+        assert_eq!(h.percentile(0.25), 10.0);
+        assert!((h.percentile(0.625) - 15.0).abs() < 1e-12);
+        assert_eq!(h.percentile(1.0), 20.0);
+    }
+
+    #[test]
+    fn moments_come_from_the_raw_values() {
+        let mut h = Histogram::new(&[0.0, 100.0]);
+        for x in [1.0, 2.0, 3.0, 10.0] {
+            h.add(x);
+        }
+        assert_eq!(h.mean(), 4.0);
+        assert_eq!(h.variance(), 12.5);
+        assert!(h.skew() > 0.0);
+    }
+}
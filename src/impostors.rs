@@ -0,0 +1,78 @@
+use rand::rngs::StdRng;
+use rand::seq::index;
+
+
+/// Fraction of the feature columns each iteration compares on.
+const FEATURE_FRACTION: f64 = 0.5;
+/// Fraction of the impostor pool each iteration compares against.
+const IMPOSTOR_FRACTION: f64 = 0.5;
+
+
+/// Koppel & Seidman's General Impostors verification. Each iteration picks a random
+/// subset of the feature `columns` and a random subset of the `impostors`, and asks
+/// whether the questioned document is more similar to one of the `candidate`'s
+/// documents than to every chosen impostor. The score is the fraction of iterations
+/// where it is: near 1 means the candidate wins however the features and impostors
+/// are drawn.
+pub fn score(questioned: &[f64], candidate: &[Vec<f64>], impostors: &[Vec<f64>], columns: &[usize], iterations: usize, rng: &mut StdRng) -> f64 {
+    let features = ((FEATURE_FRACTION * columns.len() as f64).round() as usize).max(1);
+    let rivals = ((IMPOSTOR_FRACTION * impostors.len() as f64).round() as usize).clamp(1, impostors.len().max(1));
+
+    let mut wins = 0;
+    for _iteration in 0..iterations {
+        let chosen: Vec<usize> = index::sample(rng, columns.len(), features).iter().map(|i| columns[i]).collect();
+        let nearest = |documents: &mut dyn Iterator<Item = &Vec<f64>>| -> f64 {
+            documents.map(|d| cosine(questioned, d, &chosen)).fold(f64::NEG_INFINITY, f64::max)
+        };
+
+        let candidate_similarity = nearest(&mut candidate.iter());
+        let sampled = if impostors.is_empty() { Vec::new() } else { index::sample(rng, impostors.len(), rivals).into_vec() };
+        let impostor_similarity = nearest(&mut sampled.iter().map(|i| &impostors[*i]));
+        if candidate_similarity > impostor_similarity {
+            wins += 1;
+        }
+    }
+    wins as f64 / iterations.max(1) as f64
+}
+
+
+/// Cosine similarity restricted to `columns`; 0 if either vector is zero there.
+fn cosine(a: &[f64], b: &[f64], columns: &[usize]) -> f64 {
+    let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+    for j in columns {
+        ab += a[*j] * b[*j];
+        aa += a[*j] * a[*j];
+        bb += b[*j] * b[*j];
+    }
+    if aa > 0.0 && bb > 0.0 {
+        ab / (aa * bb).sqrt()
+    } else {
+        0.0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn cosine_only_looks_at_the_chosen_columns() {
+        let (a, b) = ([1.0, 0.0, 5.0], [1.0, 3.0, 5.0]);
+        assert!((cosine(&a, &b, &[0, 2]) - 1.0).abs() < 1e-12);
+        assert!(cosine(&a, &b, &[1]).abs() < 1e-12);
+        assert!((cosine(&a, &b, &[0, 1]) - 1.0 / 10f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn score_is_the_fraction_of_iterations_the_candidate_wins() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let candidate = vec![vec![4.0, 1.0, 1.0, 2.0], vec![3.0, 1.0, 2.0, 1.0]];
+        let impostors = vec![vec![1.0, 4.0, 2.0, 5.0]];
+        let columns = [0, 1, 2, 3];
+        assert_eq!(score(&candidate[0], &candidate, &impostors, &columns, 50, &mut rng), 1.0);
+        assert_eq!(score(&impostors[0], &candidate, &impostors, &columns, 50, &mut rng), 0.0);
+        assert_eq!(score(&candidate[0], &candidate, &[], &columns, 10, &mut rng), 1.0);
+    }
+}
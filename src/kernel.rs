@@ -0,0 +1,70 @@
+use std::fmt;
+use crate::perceptron::Sample;
+
+
+/// Kernels shared by the SVM and the kernel perceptron. Each is an inner product in
+/// some feature space, so either model can learn boundaries that aren't linear in
+/// the original features.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel {
+    /// The plain dot product.
+    Linear,
+    /// `(x . y + offset)^degree`
+    Polynomial { degree: i32, offset: f64 },
+    /// Gaussian radial basis function, `exp(-gamma * |x - y|^2)`.
+    Rbf { gamma: f64 }
+}
+
+impl Kernel {
+    /// Parses `linear`, `poly:D`, `poly:D:C` or `rbf:G`.
+    pub fn parse(spec: &str) -> Result<Kernel, String> {
+        let usage = || format!("Unknown kernel `{}` (expected linear, poly:D, poly:D:C or rbf:G)", spec);
+        let parts: Vec<&str> = spec.split(':').collect();
+        match parts.as_slice() {
+            ["linear"] => Ok(Kernel::Linear),
+            ["poly", degree] => Ok(Kernel::Polynomial { degree: degree.parse().map_err(|_| usage())?, offset: 1.0 }),
+            ["poly", degree, offset] => Ok(Kernel::Polynomial {
+                degree: degree.parse().map_err(|_| usage())?,
+                offset: offset.parse().map_err(|_| usage())?
+            }),
+            ["rbf", gamma] => Ok(Kernel::Rbf { gamma: gamma.parse().map_err(|_| usage())? }),
+            _ => Err(usage())
+        }
+    }
+
+    pub fn apply(&self, a: &[f64], b: &[f64]) -> f64 {
+        match self {
+            Kernel::Linear => dot(a, b),
+            Kernel::Polynomial { degree, offset } => (dot(a, b) + offset).powi(*degree),
+            Kernel::Rbf { gamma } => {
+                assert_eq!(a.len(), b.len());
+                let distance: f64 = a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum();
+                (-gamma * distance).exp()
+            }
+        }
+    }
+
+    /// The kernel between every pair of samples.
+    pub fn gram(&self, samples: &[Sample]) -> Vec<Vec<f64>> {
+        samples.iter()
+            .map(|a| samples.iter().map(|b| self.apply(&a.values, &b.values)).collect())
+            .collect()
+    }
+}
+
+/// The spec accepted by `Kernel::parse`.
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kernel::Linear => write!(f, "linear"),
+            Kernel::Polynomial { degree, offset } => write!(f, "poly:{}:{}", degree, offset),
+            Kernel::Rbf { gamma } => write!(f, "rbf:{}", gamma)
+        }
+    }
+}
+
+
+pub fn dot(v1: &[f64], v2: &[f64]) -> f64 {
+    assert_eq!(v1.len(), v2.len());
+    v1.iter().zip(v2.iter()).map(|(x, y)| x * y).sum()
+}
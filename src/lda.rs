@@ -0,0 +1,148 @@
+use crate::kernel::dot;
+use crate::linalg::{self, Matrix};
+use crate::neighbours;
+use crate::perceptron::Sample;
+
+
+/// Fisher's linear discriminant analysis. The discriminant axes are the directions
+/// that maximise the spread between the class means relative to the spread within
+/// the classes; projected onto them, each class is as tight and as far from the
+/// others as a linear map can make it. At most one fewer axes than classes carry any
+/// between-class spread.
+#[derive(Debug, Clone)]
+pub struct Lda {
+    pub classes: Vec<i8>,
+    pub log_priors: Vec<f64>,
+    /// The overall mean, subtracted before projecting.
+    pub mean: Vec<f64>,
+    /// Scaled so that the within-class variance along each is 1.
+    pub axes: Matrix,
+    /// Between-class to within-class variance along each axis, largest first.
+    pub ratios: Vec<f64>,
+    /// The class means, projected.
+    pub centroids: Matrix
+}
+
+impl Lda {
+    /// `ridge` is added to the within-class covariance's diagonal, as a fraction of its
+    /// mean variance; with more features than samples that covariance is singular
+    /// without it.
+    pub fn fit(samples: &[Sample], ridge: f64) -> Result<Lda, String> {
+        let mut classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+        classes.sort_unstable();
+        classes.dedup();
+        neighbours::check_classes("Discriminant analysis", &classes)?;
+        let n = samples.len() as f64;
+        let dims = samples[0].values.len();
+
+        let rows: Vec<&[f64]> = samples.iter().map(|s| s.values.as_slice()).collect();
+        let (mean, _) = linalg::covariance(&rows);
+        let mut within = vec![vec![0.0; dims]; dims];
+        let mut between = vec![vec![0.0; dims]; dims];
+        let mut class_means = Vec::new();
+        let mut log_priors = Vec::new();
+        for class in &classes {
+            let members: Vec<&[f64]> = samples.iter().filter(|s| s.class == *class).map(|s| s.values.as_slice()).collect();
+            let (class_mean, covariance) = linalg::covariance(&members);
+            let size = members.len() as f64;
+            for i in 0..dims {
+                for j in 0..dims {
+                    within[i][j] += covariance[i][j] * (size - 1.0).max(0.0) / (n - classes.len() as f64).max(1.0);
+                    between[i][j] += size * (class_mean[i] - mean[i]) * (class_mean[j] - mean[j]) / (classes.len() - 1) as f64;
+                }
+            }
+            class_means.push(class_mean);
+            log_priors.push((size / n).ln());
+        }
+
+        let average = (0..dims).map(|i| within[i][i]).sum::<f64>() / dims as f64;
+        for (i, row) in within.iter_mut().enumerate() {
+            row[i] += ridge * average + 1e-12;
+        }
+
+        // with within = L L', the axes are L'^-1 times the eigenvectors of L^-1 between L'^-1
+        let inverse = linalg::invert_lower(&linalg::cholesky(&within)?);
+        let whitened = linalg::multiply(&linalg::multiply(&inverse, &between), &linalg::transpose(&inverse));
+        let (values, vectors) = linalg::symmetric_eigen(&whitened);
+        let kept = (classes.len() - 1).min(dims);
+        let inverse_t = linalg::transpose(&inverse);
+        let axes: Matrix = vectors.iter().take(kept)
+            .map(|v| inverse_t.iter().map(|row| dot(row, v)).collect())
+            .collect();
+
+        let mut lda = Lda { classes, log_priors, mean, axes, ratios: values.into_iter().take(kept).collect(), centroids: Vec::new() };
+        lda.centroids = class_means.iter().map(|m| lda.project(m)).collect();
+        Ok(lda)
+    }
+
+    /// The coordinates of `values` along each discriminant axis.
+    pub fn project(&self, values: &[f64]) -> Vec<f64> {
+        let centred: Vec<f64> = values.iter().zip(&self.mean).map(|(x, m)| x - m).collect();
+        self.axes.iter().map(|axis| dot(axis, &centred)).collect()
+    }
+
+    /// Half the squared distance to each class's projected mean, less its log prior;
+    /// the smallest wins.
+    pub fn discriminants(&self, values: &[f64]) -> Vec<f64> {
+        let point = self.project(values);
+        self.centroids.iter().zip(&self.log_priors)
+            .map(|(centroid, prior)| 0.5 * point.iter().zip(centroid).map(|(p, c)| (p - c).powi(2)).sum::<f64>() - prior)
+            .collect()
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        self.classes[linalg::argmin(&self.discriminants(values))]
+    }
+
+    pub fn classify(&self, samples: &[Sample]) -> Vec<i8> {
+        samples.iter().map(|s| self.predict(&s.values)).collect()
+    }
+
+    /// How much nearer class 1 is than the nearest other class: positive means class 1.
+    pub fn score(&self, values: &[f64]) -> f64 {
+        neighbours::centroid_margin(&self.classes, &self.discriminants(values))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two classes apart along x, spread along y.
+    fn samples() -> Vec<Sample> {
+        [(0.2, 3.0, 0), (-0.2, 3.0, 0), (0.2, -3.0, 0), (-0.2, -3.0, 0), (4.2, 3.5, 1), (3.8, 3.5, 1), (4.2, -2.5, 1), (3.8, -2.5, 1)].iter()
+            .map(|(x, y, class)| Sample { values: vec![*x, *y], class: *class })
+            .collect()
+    }
+
+    #[test]
+    fn the_axis_follows_the_separation_not_the_spread() {
+        let model = Lda::fit(&samples(), 0.0).unwrap();
+        assert_eq!((model.axes.len(), model.ratios.len()), (1, 1));
+        let axis = &model.axes[0];
+        assert!(axis[0].abs() > 10.0 * axis[1].abs());
+        assert!(model.centroids[0][0] * model.centroids[1][0] < 0.0);
+        assert_eq!(model.classify(&samples()), samples().iter().map(|s| s.class).collect::<Vec<i8>>());
+        assert!(model.score(&[4.0, -3.0]) > 0.0 && model.score(&[0.0, 3.0]) < 0.0);
+    }
+
+    #[test]
+    fn projections_have_unit_within_class_variance() {
+        let model = Lda::fit(&samples(), 0.0).unwrap();
+        let within: f64 = samples().iter()
+            .map(|s| (model.project(&s.values)[0] - model.centroids[s.class as usize][0]).powi(2))
+            .sum::<f64>() / (samples().len() - 2) as f64;
+        assert!((within - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn needs_class_one_and_another_class() {
+        let mut without_one = samples();
+        for s in without_one.iter_mut().filter(|s| s.class == 1) {
+            s.class = 2;
+        }
+        assert!(Lda::fit(&without_one, 0.0).is_err());
+        assert!(Lda::fit(&samples()[4..], 0.0).is_err());
+    }
+}
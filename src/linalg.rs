@@ -0,0 +1,197 @@
+/// Dense matrices as rows, just enough linear algebra for the discriminant and
+/// principal component analyses.
+pub type Matrix = Vec<Vec<f64>>;
+
+
+/// Sweeps of the Jacobi eigenvalue method before giving up on convergence.
+const MAX_SWEEPS: usize = 100;
+
+
+/// The mean of the rows, and their covariance about it (dividing by n - 1).
+pub fn covariance(rows: &[&[f64]]) -> (Vec<f64>, Matrix) {
+    let n = rows.len() as f64;
+    let dims = rows[0].len();
+    let mean: Vec<f64> = (0..dims).map(|j| rows.iter().map(|r| r[j]).sum::<f64>() / n).collect();
+    let mut covariance = vec![vec![0.0; dims]; dims];
+    for r in rows {
+        let centred: Vec<f64> = r.iter().zip(&mean).map(|(x, m)| x - m).collect();
+        for (row, di) in covariance.iter_mut().zip(&centred) {
+            for (c, dj) in row.iter_mut().zip(&centred) {
+                *c += di * dj;
+            }
+        }
+    }
+    let divisor = (n - 1.0).max(1.0);
+    for c in covariance.iter_mut().flatten() {
+        *c /= divisor;
+    }
+    (mean, covariance)
+}
+
+pub fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    a.iter()
+        .map(|row| (0..b[0].len()).map(|j| row.iter().zip(b).map(|(x, b_row)| x * b_row[j]).sum()).collect())
+        .collect()
+}
+
+pub fn transpose(a: &Matrix) -> Matrix {
+    (0..a[0].len()).map(|j| a.iter().map(|row| row[j]).collect()).collect()
+}
+
+/// The index of the largest value, ties going to the first.
+pub fn argmax(values: &[f64]) -> usize {
+    let mut best = 0;
+    for (i, v) in values.iter().enumerate() {
+        if *v > values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+/// The index of the smallest value, ties going to the first.
+pub fn argmin(values: &[f64]) -> usize {
+    let mut best = 0;
+    for (i, v) in values.iter().enumerate() {
+        if *v < values[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+/// The lower triangular `L` with `L L' = a`, for symmetric positive definite `a`.
+pub fn cholesky(a: &Matrix) -> Result<Matrix, String> {
+    let n = a.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| l[i][k] * l[j][k]).sum();
+            if i == j {
+                let pivot = a[i][i] - sum;
+                if pivot <= 0.0 {
+                    return Err(format!("Matrix is not positive definite (pivot {} at row {})", pivot, i));
+                }
+                l[i][i] = pivot.sqrt();
+            } else {
+                l[i][j] = (a[i][j] - sum) / l[j][j];
+            }
+        }
+    }
+    Ok(l)
+}
+
+/// The inverse of a lower triangular matrix, by forward substitution.
+pub fn invert_lower(l: &Matrix) -> Matrix {
+    let n = l.len();
+    let mut inverse: Matrix = Vec::new();
+    for (i, row) in l.iter().enumerate() {
+        let next = (0..n).map(|c| {
+            if c > i {
+                return 0.0;
+            }
+            let target = if i == c { 1.0 } else { 0.0 };
+            let sum: f64 = (c..i).map(|k| row[k] * inverse[k][c]).sum();
+            (target - sum) / row[i]
+        }).collect();
+        inverse.push(next);
+    }
+    inverse
+}
+
+/// Eigenvalues of a symmetric matrix, largest first, and their unit eigenvectors,
+/// by cyclic Jacobi rotations.
+pub fn symmetric_eigen(a: &Matrix) -> (Vec<f64>, Matrix) {
+    let n = a.len();
+    let mut a = a.clone();
+    let mut vectors: Matrix = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+
+    let scale: f64 = a.iter().flatten().map(|x| x * x).sum::<f64>().sqrt();
+    for _sweep in 0..MAX_SWEEPS {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum::<f64>().sqrt();
+        if off <= 1e-12 * scale.max(f64::MIN_POSITIVE) {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // the rotation that zeroes a[p][q]
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (upper, lower) = a.split_at_mut(q);
+                for (apk, aqk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    let (x, y) = (*apk, *aqk);
+                    *apk = c * x - s * y;
+                    *aqk = s * x + c * y;
+                }
+                for v in vectors.iter_mut() {
+                    let (vp, vq) = (v[p], v[q]);
+                    v[p] = c * vp - s * vq;
+                    v[q] = s * vp + c * vq;
+                }
+            }
+        }
+    }
+
+    // the columns of `vectors` are the eigenvectors
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|i, j| a[*j][*j].partial_cmp(&a[*i][*i]).unwrap());
+    let values = order.iter().map(|i| a[*i][*i]).collect();
+    let vectors = order.iter().map(|i| vectors.iter().map(|row| row[*i]).collect()).collect();
+    (values, vectors)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Matrix, b: &Matrix) -> bool {
+        a.iter().flatten().zip(b.iter().flatten()).all(|(x, y)| (x - y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn covariance_divides_by_n_minus_one() {
+        let rows: Vec<&[f64]> = vec![&[1.0, 2.0], &[3.0, 6.0], &[5.0, 4.0]];
+        let (mean, covariance) = covariance(&rows);
+        assert_eq!(mean, vec![3.0, 4.0]);
+        assert!(close(&covariance, &vec![vec![4.0, 2.0], vec![2.0, 4.0]]));
+    }
+
+    #[test]
+    fn cholesky_factors_a_known_matrix_and_refuses_an_indefinite_one() {
+        let a = vec![vec![4.0, 12.0, -16.0], vec![12.0, 37.0, -43.0], vec![-16.0, -43.0, 98.0]];
+        let l = cholesky(&a).unwrap();
+        assert!(close(&l, &vec![vec![2.0, 0.0, 0.0], vec![6.0, 1.0, 0.0], vec![-8.0, 5.0, 3.0]]));
+        assert!(close(&multiply(&l, &transpose(&l)), &a));
+
+        let identity: Matrix = (0..3).map(|i| (0..3).map(|j| (i == j) as u8 as f64).collect()).collect();
+        assert!(close(&multiply(&l, &invert_lower(&l)), &identity));
+
+        assert!(cholesky(&vec![vec![1.0, 2.0], vec![2.0, 1.0]]).is_err());
+    }
+
+    #[test]
+    fn jacobi_finds_known_eigenpairs_largest_first() {
+        let a = vec![vec![2.0, 0.0, 0.0], vec![0.0, 3.0, 4.0], vec![0.0, 4.0, 9.0]];
+        let (values, vectors) = symmetric_eigen(&a);
+        assert!(values.iter().zip(&[11.0, 2.0, 1.0]).all(|(v, expected)| (v - expected).abs() < 1e-9));
+        for (value, vector) in values.iter().zip(&vectors) {
+            let image = multiply(&a, &transpose(&vec![vector.clone()]));
+            let scaled: Matrix = vector.iter().map(|v| vec![value * v]).collect();
+            assert!(close(&image, &scaled));
+            assert!((vector.iter().map(|v| v * v).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        // the top eigenvector of the 2x2 block is (1, 2) / sqrt(5), up to sign
+        assert!((vectors[0][2] / vectors[0][1] - 2.0).abs() < 1e-9);
+    }
+}
@@ -1,15 +1,49 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::fmt;
-use rand::Rng;
 use regex::Regex;
 use glob::glob;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 
 mod nlp;
 mod svm;
+mod kernel;
 mod perceptron;
 mod models;
+mod histogram;
+mod features;
+mod scaling;
+mod selection;
+mod persist;
+mod options;
+mod eval;
+mod calibration;
+mod openset;
+mod impostors;
+mod unmasking;
+mod svg;
+mod ppm;
+mod naive_bayes;
+mod logistic;
+mod neighbours;
+mod forest;
+mod mlp;
+mod linalg;
+mod lda;
+mod pca;
+
+use histogram::Histogram;
+use features::Pipeline;
+use persist::{Model, SavedModel};
+use perceptron::PerceptronConfig;
+use options::{Options, positional};
+use eval::ConfusionMatrix;
+use calibration::{Calibration, Calibrator};
+use kernel::Kernel;
+use scaling::Scaling;
 
 #[derive(Debug)]
 struct CorpusData {
@@ -20,6 +54,11 @@ struct CorpusData {
     pronouns_per_sentence: Vec<i16>,
     conjunctions_per_sentence: Vec<i16>,
     word_frequencies: HashMap<String, i16>,
+    char_ngrams: HashMap<String, i32>,
+    italic_spans: i32,
+    capitalised_words: i32,
+    /// The book as read, for models that work on characters rather than features.
+    text: String,
     class: u8
 }
 
@@ -32,47 +71,70 @@ struct CorpusStats {
     hapax_legomena: i32,
     dis_legomena: i32,
     unique_words: i32,
-    sentence_length_dist: [f64; 36],
-    word_length_dist: [f64; 26],
-    pronouns_per_sentence_dist: [f64; 20],
-    conjunctions_per_sentence_dist: [f64; 20],
+    sentence_length_dist: Histogram,
+    word_length_dist: Histogram,
+    pronouns_per_sentence_dist: Histogram,
+    conjunctions_per_sentence_dist: Histogram,
+    italic_rate: f64,
+    capitalised_rate: f64,
+    syllables_per_word: f64,
+    syllables_per_word_dist: Histogram,
+    polysyllable_rate: f64,
+    flesch_reading_ease: f64,
+    flesch_kincaid_grade: f64,
+    word_frequencies: HashMap<String, f64>,
+    char_ngram_frequencies: HashMap<String, f64>,
+    /// Raw word counts, for models such as naive Bayes that want counts rather than rates.
+    word_counts: HashMap<String, f64>,
+    text: String,
     class: u8
 }
 
 impl fmt::Display for CorpusStats {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut word_length_string = String::new();
-        let mut sentence_length_string = String::new();
-        let mut pronoun_dist_string = String::new();
-        let mut conjunctions_dist_string = String::new();
-        for l in self.word_length_dist.iter() {
-            word_length_string = format!("{}  {:.2}", word_length_string, l);
-        }
-        for l in self.sentence_length_dist.iter() {
-            sentence_length_string = format!("{}  {:.2}", sentence_length_string, l);
-        }
-        for l in self.pronouns_per_sentence_dist.iter() {
-            pronoun_dist_string = format!("{}  {:.2}", pronoun_dist_string, l);
-        }
-        for l in self.conjunctions_per_sentence_dist.iter() {
-            conjunctions_dist_string = format!("{}  {:.2}", conjunctions_dist_string, l);
+        writeln!(f, "=====================================================================================================================")?;
+        writeln!(f, "|  Dataset: {} ({} words in {} sentences)", self.name, self.total_words, self.total_sentences)?;
+        writeln!(f, "=====================================================================================================================")?;
+        writeln!(f, "|  unique words: {}   |   hapax legomena: {}   |   dis legomena: {}   |   commas: {} ", self.unique_words, self.hapax_legomena, self.dis_legomena, self.total_commas)?;
+        writeln!(f, "|  italic spans per word: {:.4}   |   capitalised non-initial words per word: {:.4} ", self.italic_rate, self.capitalised_rate)?;
+        writeln!(f, "|  syllables per word: {:.3}   |   polysyllables: {:.3}   |   Flesch reading ease: {:.1}   |   Flesch-Kincaid grade: {:.1} ", self.syllables_per_word, self.polysyllable_rate, self.flesch_reading_ease, self.flesch_kincaid_grade)?;
+        writeln!(f, "|  word lengths:              {} ", self.word_length_dist)?;
+        writeln!(f, "|  syllables per word:        {} ", self.syllables_per_word_dist)?;
+        writeln!(f, "|  sentence lengths:          {} ", self.sentence_length_dist)?;
+        writeln!(f, "|  pronouns per sentence:     {} ", self.pronouns_per_sentence_dist)?;
+        writeln!(f, "|  conjunctions per sentence: {} ", self.conjunctions_per_sentence_dist)?;
+        writeln!(f)
+    }
+}
+
+
+/// Bin edges for each of the `CorpusStats` distributions. The defaults reproduce the
+/// original fixed-size arrays, except that values past the last bin now go to the
+/// histogram's overflow instead of being lumped into the final bin.
+#[derive(Debug, Clone)]
+struct Binning {
+    sentence_length: Vec<f64>,
+    word_length: Vec<f64>,
+    pronouns_per_sentence: Vec<f64>,
+    conjunctions_per_sentence: Vec<f64>,
+    syllables_per_word: Vec<f64>
+}
+
+impl Default for Binning {
+    fn default() -> Binning {
+        Binning {
+            sentence_length: Histogram::uniform_edges(0.5, 3.0, 36),
+            word_length: Histogram::uniform_edges(0.5, 1.0, 26),
+            pronouns_per_sentence: Histogram::uniform_edges(-0.5, 1.0, 20),
+            conjunctions_per_sentence: Histogram::uniform_edges(-0.5, 1.0, 20),
+            syllables_per_word: Histogram::uniform_edges(0.5, 1.0, 8)
         }
-        
-        write!(f, "=====================================================================================================================\n")?;
-        write!(f, "|  Dataset: {} ({} words in {} sentences)\n", self.name, self.total_words, self.total_sentences)?;
-        write!(f, "=====================================================================================================================\n")?;
-        write!(f, "|  unique words: {}   |   hapax legomena: {}   |   dis legomena: {}   |   commas: {} \n", self.unique_words, self.hapax_legomena, self.dis_legomena, self.total_commas)?;
-        write!(f, "|  word lengths:              {} \n", word_length_string)?;
-        write!(f, "|  sentence lengths:          {} \n", sentence_length_string)?;
-        write!(f, "|  pronouns per sentence:     {} \n", pronoun_dist_string)?;
-        write!(f, "|  conjunctions per sentence: {} \n", conjunctions_dist_string)?;
-        write!(f, "\n")
     }
 }
 
 
 
-fn statistics(corpus_data: CorpusData) -> CorpusStats {
+fn statistics(corpus_data: CorpusData, binning: &Binning) -> CorpusStats {
 
     let sentence_lengths = corpus_data.words_per_sentence;
     let pronouns_per_sentence = corpus_data.pronouns_per_sentence;
@@ -83,25 +145,32 @@ fn statistics(corpus_data: CorpusData) -> CorpusStats {
     let mut hapax_legomena = 0; // (fraction of) words used once
     let mut dis_legomena = 0; // (fraction of) words used twice
     let mut unique_words = 0;
-    let mut sentence_length_dist: [i16; 36] = [0; 36];
-    let mut word_length_dist: [i32; 26] = [0; 26];
-    let mut pronouns_per_sentence_dist: [i16; 20] = [0; 20];
-    let mut conjunctions_per_sentence_dist: [i16; 20] = [0; 20];
+    let mut sentence_length_dist = Histogram::new(&binning.sentence_length);
+    let mut word_length_dist = Histogram::new(&binning.word_length);
+    let mut pronouns_per_sentence_dist = Histogram::new(&binning.pronouns_per_sentence);
+    let mut conjunctions_per_sentence_dist = Histogram::new(&binning.conjunctions_per_sentence);
+    let mut syllables_per_word_dist = Histogram::new(&binning.syllables_per_word);
+    let mut total_syllables: i32 = 0;
+    let mut polysyllables: i32 = 0;
 
     
-    for (word, count) in &*word_frequencies {
+    for (word, count) in word_frequencies {
         let length: usize = word.chars().count();
-        if length > 26 {
-           // println!("Long word alert: {:?}", word);
-            word_length_dist[25] += 1;
-        } else if length > 0 {
-            word_length_dist[length-1] += 1;
-        } else {
+        if length == 0 {
             continue;
         }
+        word_length_dist.add(length as f64);
 
         unique_words += 1;
         total_words += *count as i32;
+
+        // unlike word lengths, syllables are counted per token rather than per unique word
+        let syllables = nlp::syllables(word);
+        syllables_per_word_dist.add_weighted(syllables as f64, *count as f64);
+        total_syllables += (syllables as i32) * (*count as i32);
+        if syllables >= 3 {
+            polysyllables += *count as i32;
+        }
         if *count == 1 {
             hapax_legomena += 1;
         } else if *count == 2 {
@@ -111,111 +180,117 @@ fn statistics(corpus_data: CorpusData) -> CorpusStats {
     // word_counts.clear();
     
     for l in sentence_lengths {
-        let l = l as usize;
-        if l > 3*36 {
-            sentence_length_dist[35] += 1;
-        } else if l > 0 {
-            sentence_length_dist[(l-1)/3] += 1;
-        } else {
-            continue;
+        if l > 0 {
+            sentence_length_dist.add(l as f64);
         }
     }
     
     for l in pronouns_per_sentence {
-        let l = l as usize;
-        if l > 19 {
-            pronouns_per_sentence_dist[19] += 1;
-        } else {
-            pronouns_per_sentence_dist[l] += 1;
-        }
+        pronouns_per_sentence_dist.add(l as f64);
     }
     
     for l in conjunctions_per_sentence {
-        let l = l as usize;
-        if l > 19 {
-            conjunctions_per_sentence_dist[19] += 1;
-        } else {
-            conjunctions_per_sentence_dist[l] += 1;
-        }
+        conjunctions_per_sentence_dist.add(l as f64);
     }
-    
-    let mut sentence_length_dist_f64: [f64; 36] = [0.0; 36];
-    let mut word_length_dist_f64: [f64; 26] = [0.0; 26];
-    let mut pronouns_per_sentence_dist_f64: [f64; 20] = [0.0; 20];
-    let mut conjunctions_per_sentence_dist_f64: [f64; 20] = [0.0; 20];
 
-    for (i, e) in sentence_length_dist.iter().enumerate() {
-        sentence_length_dist_f64[i] = (*e as f64) / (corpus_data.total_sentences as f64);
-    }
-    
-    for (i, e) in word_length_dist.iter().enumerate() {
-        word_length_dist_f64[i] = (*e as f64) / (unique_words as f64);
-    }
-    
-    for (i, e) in pronouns_per_sentence_dist.iter().enumerate() {
-        pronouns_per_sentence_dist_f64[i] = (*e as f64) / (corpus_data.total_sentences as f64);
-    }
-    
-    for (i, e) in conjunctions_per_sentence_dist.iter().enumerate() {
-        conjunctions_per_sentence_dist_f64[i] = (*e as f64) / (corpus_data.total_sentences as f64);
+    let words_per_sentence = total_words as f64 / corpus_data.total_sentences as f64;
+    let syllables_per_word = total_syllables as f64 / total_words as f64;
+    let flesch_reading_ease = 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word;
+    let flesch_kincaid_grade = 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59;
+
+    // relative frequencies, so that long and short books are comparable
+    let mut relative_word_frequencies = HashMap::new();
+    let mut word_counts = HashMap::new();
+    for (word, count) in word_frequencies {
+        if !word.is_empty() {
+            relative_word_frequencies.insert(word.clone(), *count as f64 / total_words as f64);
+            word_counts.insert(word.clone(), *count as f64);
+        }
     }
+    let total_ngrams: i32 = corpus_data.char_ngrams.values().sum();
+    let char_ngram_frequencies = corpus_data.char_ngrams.iter()
+        .map(|(gram, count)| (gram.clone(), *count as f64 / total_ngrams as f64))
+        .collect();
+
+    let italic_rate = corpus_data.italic_spans as f64 / total_words as f64;
+    let capitalised_rate = corpus_data.capitalised_words as f64 / total_words as f64;
 
     CorpusStats {
         name: corpus_data.name,
         total_sentences: corpus_data.total_sentences,
         total_commas: corpus_data.total_commas,
-        total_words: total_words,
-        hapax_legomena: hapax_legomena,
-        dis_legomena: dis_legomena,
-        unique_words: unique_words,
-        sentence_length_dist: sentence_length_dist_f64,
-        word_length_dist: word_length_dist_f64,
-        pronouns_per_sentence_dist: pronouns_per_sentence_dist_f64,
-        conjunctions_per_sentence_dist: conjunctions_per_sentence_dist_f64,
+        total_words,
+        hapax_legomena,
+        dis_legomena,
+        unique_words,
+        sentence_length_dist,
+        word_length_dist,
+        pronouns_per_sentence_dist,
+        conjunctions_per_sentence_dist,
+        italic_rate,
+        capitalised_rate,
+        syllables_per_word,
+        syllables_per_word_dist,
+        polysyllable_rate: polysyllables as f64 / total_words as f64,
+        flesch_reading_ease,
+        flesch_kincaid_grade,
+        word_frequencies: relative_word_frequencies,
+        char_ngram_frequencies,
+        word_counts,
+        text: corpus_data.text,
         class: corpus_data.class
     }
 }
 
 
-fn read_book(fname: &str, class: u8) -> CorpusData {
+fn read_book(path: &Path, class: u8) -> CorpusData {
     // let fname = "data/test.txt";
+    let fname = path.file_name().unwrap().to_str().unwrap();
     println!("\nImporting file: {}", fname);
-    let filename = format!("data/corpus/{}", fname);
 
     let mut total_sentences = 0;
     let mut count_words = 0;
     let mut count_pronouns = 0;
     let mut count_conjunctions = 0;
     let mut count_commas = 0;
+    let mut italic_spans = 0;
+    let mut capitalised_words = 0;
+    let mut sentence_start = true;
 
-    let file = File::open(filename).expect("No such file");
+    let file = File::open(path).expect("No such file");
     let buf = BufReader::new(file);
     let text: Vec<String> = buf.lines().map(|l| l.expect("Could not parse line")).collect();
+    let raw = text.join("\n");
     let mut words_per_sentence: Vec<i16> = Vec::new();
     let mut pronouns_per_sentence: Vec<i16> = Vec::new();
     let mut conjunctions_per_sentence: Vec<i16> = Vec::new();
     let mut map: HashMap<String, i16> = HashMap::new();
+    let mut char_ngrams: HashMap<String, i32> = HashMap::new();
 
     let mut test_sentence: String = String::new();
 
     for line in text {
         for word in line.split_whitespace() {
-            let mut keyword = word.to_lowercase().to_string();
-            keyword = keyword.replace(&['(', ')', '§', '*', '\"', '“', '”', ';', ':', '\'', '_'][..], "");
+            // Typographic features have to be read before lowercasing and punctuation
+            // stripping below throw the capitals and `_italic_` markers away.
+            if nlp::opens_italics(word) {
+                italic_spans += 1;
+            }
+            if !sentence_start && nlp::is_capitalised(word) {
+                capitalised_words += 1;
+            }
+            sentence_start = false;
+
+            let (keyword, ends_sentence) = nlp::normalise(word);
             count_words += 1;
             test_sentence.push_str(&keyword);
-            test_sentence.push_str(" ");
-
+            test_sentence.push(' ');
 
-            if keyword.contains(',') {
+            if word.contains(',') {
                 count_commas += 1;
-                keyword = keyword.replace(&[','][..], "");
             }
 
-            keyword = nlp::replace_word(keyword);
-
-            if keyword.contains('.') ||  keyword.contains('?') ||  keyword.contains('!') {
-                keyword = keyword.replace(&['.', '?', '!'][..], "");
+            if ends_sentence {
                 total_sentences += 1;
                 words_per_sentence.push(count_words);
                 pronouns_per_sentence.push(count_pronouns);
@@ -224,6 +299,7 @@ fn read_book(fname: &str, class: u8) -> CorpusData {
                 count_pronouns = 0;
                 count_conjunctions = 0;
                 test_sentence = String::new();
+                sentence_start = true;
             }
 
             if nlp::is_pronoun(word) {
@@ -234,6 +310,12 @@ fn read_book(fname: &str, class: u8) -> CorpusData {
                 count_conjunctions += 1;
             } 
 
+            if !keyword.is_empty() {
+                for gram in nlp::char_ngrams(&keyword, 3) {
+                    *char_ngrams.entry(gram).or_insert(0) += 1;
+                }
+            }
+
             let count = map.entry(keyword).or_insert(0);
             *count += 1;
         }
@@ -241,13 +323,17 @@ fn read_book(fname: &str, class: u8) -> CorpusData {
     
     CorpusData {
         name: String::from(fname),
-        total_sentences: total_sentences,
+        total_sentences,
         total_commas: count_commas,
-        words_per_sentence: words_per_sentence,
-        pronouns_per_sentence: pronouns_per_sentence,
-        conjunctions_per_sentence: conjunctions_per_sentence,
+        words_per_sentence,
+        pronouns_per_sentence,
+        conjunctions_per_sentence,
         word_frequencies: map,
-        class: class
+        char_ngrams,
+        italic_spans,
+        capitalised_words,
+        text: raw,
+        class
     }
 }
 
@@ -269,184 +355,813 @@ fn read_iris_data() -> Vec<models::Iris> {
 
     for line in text {
 
-        match re.captures(&line[..]) {
-            Some(cap) => {
-                let groups = (cap.get(1), cap.get(2), cap.get(3), cap.get(4), cap.get(5));
-                match groups {
-                    (Some(sl), Some(sw), Some(pl), Some(pw), Some(n)) => {
-                        let new_iris = models::Iris {
-                            sepal_length: sl.as_str().parse::<f64>().unwrap(),
-                            sepal_width: sw.as_str().parse::<f64>().unwrap(),
-                            petal_length: pl.as_str().parse::<f64>().unwrap(),
-                            petal_width: pw.as_str().parse::<f64>().unwrap(),
-                            class: String::from(n.as_str())
-                        };
-
-                        collection.push(new_iris);
-                    },
-                    _ => ()
+        if let Some(cap) = re.captures(&line[..]) {
+            let groups = (cap.get(1), cap.get(2), cap.get(3), cap.get(4), cap.get(5));
+            if let (Some(sl), Some(sw), Some(pl), Some(pw), Some(n)) = groups {
+                let new_iris = models::Iris {
+                    sepal_length: sl.as_str().parse::<f64>().unwrap(),
+                    sepal_width: sw.as_str().parse::<f64>().unwrap(),
+                    petal_length: pl.as_str().parse::<f64>().unwrap(),
+                    petal_width: pw.as_str().parse::<f64>().unwrap(),
+                    class: String::from(n.as_str())
                 };
-            },
-            _ => ()
-        };
+
+                collection.push(new_iris);
+            }
+        }
     }
 
     collection
 }
 
 
-fn iris_perceptron(irises: &Vec<models::Iris>, iris_species: &str, testing_fraction: f64) {
-    let mut training_set: Vec<perceptron::Sample> = Vec::new();
-    let mut testing_set: Vec<perceptron::Sample> = Vec::new();
-
+/// Splits the irises at random into training and testing samples, `testing_fraction`
+/// of them held out. The values are the four measurements, after a constant 1.0 if
+/// the model needs a bias term.
+fn split_irises<F: Fn(&models::Iris) -> i8>(irises: &[models::Iris], testing_fraction: f64, bias: bool, class: F, rng: &mut StdRng) -> (Vec<perceptron::Sample>, Vec<perceptron::Sample>) {
     let total_count = irises.len();
     let testing_count = (testing_fraction * total_count as f64).round() as usize;
-    let mut rng = rand::thread_rng();
-    let special_set = rand::seq::index::sample(&mut rng, total_count, testing_count).into_vec();
-
-    println!("\nTesting iris perceptron with *{}* (training set size: {}, testing set size: {})", iris_species, total_count-testing_count, testing_count);
-
-    for (i, iris) in (&irises).iter().enumerate() {
+    let special_set = rand::seq::index::sample(rng, total_count, testing_count).into_vec();
+
+    let mut training_set = Vec::new();
+    let mut testing_set = Vec::new();
+    for (i, iris) in irises.iter().enumerate() {
+        let measurements = [iris.sepal_length, iris.sepal_width, iris.petal_length, iris.petal_width];
+        let values = if bias { std::iter::once(1.0).chain(measurements).collect() } else { measurements.to_vec() };
+        let sample = perceptron::Sample { values, class: class(iris) };
         if special_set.contains(&i) {
-            if iris.class == iris_species {
-                testing_set.push(perceptron::Sample { values: vec![1.0, iris.sepal_length, iris.sepal_width, iris.petal_length, iris.petal_width], class: 1} )
-            } else {
-                testing_set.push(perceptron::Sample { values: vec![1.0, iris.sepal_length, iris.sepal_width, iris.petal_length, iris.petal_width], class: 0} )
-            }
+            testing_set.push(sample);
         } else {
-            if iris.class == iris_species {
-                training_set.push(perceptron::Sample { values: vec![1.0, iris.sepal_length, iris.sepal_width, iris.petal_length, iris.petal_width], class: 1} )
-            } else {
-                training_set.push(perceptron::Sample { values: vec![1.0, iris.sepal_length, iris.sepal_width, iris.petal_length, iris.petal_width], class: 0} )
-            }
+            training_set.push(sample);
         }
     }
+    (training_set, testing_set)
+}
+
+/// The position of the iris's species in `species`.
+fn species_index(species: &[&str], iris: &models::Iris) -> i8 {
+    species.iter().position(|s| *s == iris.class).expect("Unknown iris species") as i8
+}
+
+
+fn iris_perceptron(irises: &[models::Iris], iris_species: &str, testing_fraction: f64, config: &PerceptronConfig, rng: &mut StdRng) {
+    let (training_set, testing_set) = split_irises(irises, testing_fraction, true, |iris| (iris.class == iris_species) as i8, rng);
+
+    println!("\nTesting iris perceptron with *{}* (training set size: {}, testing set size: {})", iris_species, training_set.len(), testing_set.len());
+
+    let result = perceptron::train(&training_set, config, rng);
+    println!("{}", result);
+    perceptron::test(&result, &testing_set);
+}
+
+
+/// One perceptron over all three species at once, so each iris gets a single predicted species.
+fn iris_multiclass(irises: &[models::Iris], species: &[&str], testing_fraction: f64, config: &PerceptronConfig, rng: &mut StdRng) {
+    let (training_set, testing_set) = split_irises(irises, testing_fraction, true, |iris| species_index(species, iris), rng);
+
+    println!("\nTesting multi-class iris perceptron (training set size: {}, testing set size: {})", training_set.len(), testing_set.len());
+
+    let model = perceptron::train_multiclass(&training_set, species.len(), config, rng);
+    println!("{}", model);
+
+    let mut confusion = ConfusionMatrix::new(species);
+    for (s, predicted) in testing_set.iter().zip(model.classify(&testing_set)) {
+        confusion.add(s.class as usize, predicted as usize);
+    }
+    println!("{}", confusion);
+}
+
+
+/// A multi-layer perceptron over all three species, on standardised measurements.
+fn iris_mlp(irises: &[models::Iris], species: &[&str], testing_fraction: f64, config: &mlp::MlpConfig, rng: &mut StdRng) {
+    let (training_set, testing_set) = split_irises(irises, testing_fraction, false, |iris| species_index(species, iris), rng);
+
+    println!("\nTesting iris MLP (training set size: {}, testing set size: {})", training_set.len(), testing_set.len());
+
+    let names: Vec<String> = ["sepal_length", "sepal_width", "petal_length", "petal_width"].iter().map(|n| n.to_string()).collect();
+    let scaler = scaling::Scaler::fit(Scaling::ZScore, &names, &training_set);
+    let model = mlp::Mlp::train(&scaler.transform_all(&training_set), config, rng);
+    println!("{}", model);
 
-    let model = perceptron::train(&training_set);
-    perceptron::test(model, &testing_set);
+    let mut confusion = ConfusionMatrix::new(species);
+    for (s, predicted) in testing_set.iter().zip(model.classify(&scaler.transform_all(&testing_set))) {
+        confusion.add(s.class as usize, predicted as usize);
+    }
+    println!("{}", confusion);
 }
 
 
+/// Fisher's discriminant analysis on his own data: the species projected onto the two
+/// discriminant axes, and classified by the nearest projected species mean.
+fn iris_lda(irises: &[models::Iris], species: &[&str], testing_fraction: f64, ridge: f64, rng: &mut StdRng) {
+    let (training_set, testing_set) = split_irises(irises, testing_fraction, false, |iris| species_index(species, iris), rng);
+
+    println!("\nTesting iris linear discriminant analysis (training set size: {}, testing set size: {})", training_set.len(), testing_set.len());
+
+    let model = lda::Lda::fit(&training_set, ridge).unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
+    });
+    let total: f64 = model.ratios.iter().sum();
+    for (a, (axis, ratio)) in model.axes.iter().zip(&model.ratios).enumerate() {
+        let coefficients: Vec<String> = axis.iter().map(|w| format!("{:.3}", w)).collect();
+        println!("Discriminant {}: [{}] (between/within ratio {:.2}, {:.1}% of the separation)", a + 1, coefficients.join(", "), ratio, 100.0 * ratio / total);
+    }
+    for (s, centroid) in species.iter().zip(&model.centroids) {
+        let point: Vec<String> = centroid.iter().map(|x| format!("{:.3}", x)).collect();
+        println!("{} mean projects to ({})", s, point.join(", "));
+    }
+
+    let mut confusion = ConfusionMatrix::new(species);
+    for (s, predicted) in testing_set.iter().zip(model.classify(&testing_set)) {
+        confusion.add(s.class as usize, predicted as usize);
+    }
+    println!("{}", confusion);
+}
 
-fn corpus_perceptron(pos: &Vec<CorpusStats>, neg: &Vec<CorpusStats>, unknown: &Vec<CorpusStats>) {
-    let mut training_set: Vec<perceptron::Sample> = Vec::new();
-    let mut classify_set: Vec<perceptron::Sample> = Vec::new();
 
+fn corpus_perceptron(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats], rng: &mut StdRng) {
     println!("\nTraining corpus perceptron with {} positive samples, {} negative samples.", pos.len(), neg.len());
-    
-    // total_sentences: i16,
-    // total_commas: i32,
-    // total_words: i32,
-    // hapax_legomena: i32,
-    // dis_legomena: i32,
-    // unique_words: i32,
-    // sentence_length_dist: [f64; 36],
-    // word_length_dist: [f64; 26],
-    // pronouns_per_sentence_dist: [f64; 20],
-    // conjunctions_per_sentence_dist: [f64; 20],
-
-    for n in neg {
-        let sample_vec = vec![1.0,
-                              n.total_commas as f64 / n.total_sentences as f64,
-                              n.hapax_legomena as f64 / n.total_words as f64,
-                              n.dis_legomena as f64 / n.total_words as f64,
-                              n.unique_words as f64 / n.total_words as f64,
-                              ];
-        training_set.push(perceptron::Sample { class: 0, values: sample_vec } )
+
+    // only the training books get a say in the scaling
+    let pipeline = fit_pipeline(options, neg.iter().chain(pos));
+    println!("Features ({} selection, {} scaling): {}", pipeline.selector.method, pipeline.scaler.method, pipeline.names.join(", "));
+
+    let training_set = pipeline.samples(neg.iter().chain(pos));
+    let classify_set = pipeline.samples(unknown);
+
+    let result = perceptron::train(&training_set, &options.perceptron, rng);
+    println!("{}", result);
+    let calibrator = fit_calibrator("perceptron", options, pos, neg, rng);
+    print_predictions(unknown, &result.classify(&classify_set), &result.scores(&classify_set), calibrator.as_ref());
+    // perceptron::test(model, &testing_set);
+}
+
+
+fn corpus_svm(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats], rng: &mut StdRng) {
+    println!("\nTraining corpus SVM with {} positive samples, {} negative samples.", pos.len(), neg.len());
+
+    let pipeline = fit_pipeline(options, neg.iter().chain(pos));
+    let training_set = pipeline.samples(neg.iter().chain(pos));
+    let classify_set = pipeline.samples(unknown);
+
+    let model = svm::train(&training_set, 1.0, options.kernel);
+    println!("{} support vectors ({} kernel), bias {:.4}", model.support.len(), model.kernel, model.bias);
+
+    let scores: Vec<f64> = classify_set.iter().map(|s| model.decision(&s.values)).collect();
+    let classifications: Vec<i8> = scores.iter().map(|d| (*d > 0.0) as i8).collect();
+    let calibrator = fit_calibrator("svm", options, pos, neg, rng);
+    print_predictions(unknown, &classifications, &scores, calibrator.as_ref());
+}
+
+
+/// Logistic regression, whose probabilities need no separate calibration and whose
+/// weights say how far each feature moves the log odds of Defoe.
+fn corpus_logistic(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats]) {
+    println!("\nTraining corpus logistic regression with {} positive samples, {} negative samples.", pos.len(), neg.len());
+
+    let pipeline = fit_pipeline(options, neg.iter().chain(pos));
+    let model = logistic::Logistic::fit(&pipeline.samples(neg.iter().chain(pos)), options.penalty);
+    println!("{}", model);
+
+    let mut ranked: Vec<(&String, f64)> = pipeline.names.iter().zip(model.weights.iter().cloned()).filter(|(_, w)| *w != 0.0).collect();
+    ranked.sort_by(|a, b| b.1.abs().partial_cmp(&a.1.abs()).unwrap());
+    let strongest: Vec<String> = ranked.iter().take(15).map(|(name, w)| format!("{} {:+.4}", name, w)).collect();
+    println!("Strongest weights (positive is Defoe): {}", strongest.join(", "));
+
+    for (i, (s, c)) in pipeline.samples(unknown).iter().zip(unknown).enumerate() {
+        let probability = model.probability(&s.values);
+        println!("{}: Sorted {} (probability of Defoe {:.3}) for {}", i, (probability > 0.5) as i8, probability, c.name);
     }
+}
 
-    
-    for p in pos {
-        let sample_vec = vec![1.0,
-                              p.total_commas as f64 / p.total_sentences as f64,
-                              p.hapax_legomena as f64 / p.total_words as f64,
-                              p.dis_legomena as f64 / p.total_words as f64,
-                              p.unique_words as f64 / p.total_words as f64,
-                              ];
-        training_set.push(perceptron::Sample { class: 1, values: sample_vec } )
+
+/// Calibrates the decision values of a `kind` model without letting it see the books
+/// it is scored on: each fold of a stratified cross-validation is scored by a pipeline
+/// and model fitted on the other folds, and the calibrator is fitted to those scores.
+fn fit_calibrator(kind: &str, options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], rng: &mut StdRng) -> Option<Calibrator> {
+    if options.calibration == Calibration::None {
+        return None;
     }
-    
-    for u in unknown {
-        let sample_vec = vec![1.0,
-                              u.total_commas as f64 / u.total_sentences as f64,
-                              u.hapax_legomena as f64 / u.total_words as f64,
-                              u.dis_legomena as f64 / u.total_words as f64,
-                              u.unique_words as f64 / u.total_words as f64,
-                              ];
-        classify_set.push(perceptron::Sample { class: 1, values: sample_vec } )
+    // with no more folds than books in either class, every fold holds some of each
+    let smallest = pos.len().min(neg.len());
+    if options.folds > smallest {
+        println!("--folds {} is more than the {} books in the smallest class", options.folds, smallest);
+        std::process::exit(1);
+    }
+    let books: Vec<&CorpusStats> = neg.iter().chain(pos).collect();
+    let classes: Vec<i8> = books.iter().map(|b| b.class as i8).collect();
+
+    let mut scores = Vec::new();
+    let mut labels = Vec::new();
+    for fold in eval::stratified_folds(&classes, options.folds, rng).iter().filter(|f| !f.is_empty()) {
+        let training: Vec<&CorpusStats> = (0..books.len()).filter(|i| !fold.contains(i)).map(|i| books[i]).collect();
+        let held_out: Vec<&CorpusStats> = fold.iter().map(|i| books[*i]).collect();
+
+        let pipeline = fit_pipeline(options, training.iter().cloned());
+        let model = Model::train(kind, &pipeline.samples(training.iter().cloned()), options, rng, false).unwrap_or_else(|e| {
+            println!("{}", e);
+            std::process::exit(1);
+        });
+        scores.extend(model.scores(&pipeline.samples(held_out.iter().cloned())));
+        labels.extend(held_out.iter().map(|b| b.class == 1));
     }
 
-    let model = perceptron::train(&training_set);
-    let classifications = perceptron::classify(model, &classify_set);
+    println!("Fitted {} calibration to {} out-of-fold decision values ({} folds)", options.calibration, scores.len(), options.folds);
+    Calibrator::fit(options.calibration, &scores, &labels)
+}
+
 
-    for (i, (r, c)) in classifications.iter().zip(unknown.iter()).enumerate() {
-        println!("{}: Sorted {} for {}", i, r, c.name)
+/// The author part of a corpus file name: `defoe_crusoe.txt` is by `defoe`.
+fn author(name: &str) -> String {
+    let stem = name.trim_end_matches(".txt");
+    stem.split('_').next().unwrap_or(stem).to_string()
+}
+
+/// The authors of `books`, sorted, and each book's author as a position among them,
+/// for the models that tell every author apart rather than Defoe from the rest.
+fn author_labels<'a, I: IntoIterator<Item = &'a CorpusStats>>(books: I) -> (Vec<String>, Vec<i8>) {
+    let names: Vec<String> = books.into_iter().map(|b| author(&b.name)).collect();
+    let mut authors = names.clone();
+    authors.sort();
+    authors.dedup();
+    let labels = names.iter().map(|n| authors.iter().position(|a| a == n).unwrap() as i8).collect();
+    (authors, labels)
+}
+
+
+/// Attribution among every author in the training corpus, or to none of them when the
+/// nearest author profile is further away than held-out training books ever were.
+fn corpus_open_set(options: &Options, coverage: f64, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats]) {
+    let authors: Vec<String> = neg.iter().chain(pos).map(|b| author(&b.name)).collect();
+    let pipeline = fit_pipeline(options, neg.iter().chain(pos));
+    let profiles = openset::Profiles::fit(&pipeline.samples(neg.iter().chain(pos)), &authors, coverage);
+    println!("\nOpen-set attribution over {} books by {} candidate authors.", authors.len(), profiles.authors.len());
+    println!("Distance threshold {:.4} accepts {:.0}% of held-out training books", profiles.threshold, 100.0 * coverage);
+
+    for (i, (s, c)) in pipeline.samples(unknown).iter().zip(unknown).enumerate() {
+        let attribution = profiles.attribute(&s.values);
+        let nearest = &profiles.authors[attribution.nearest];
+        match attribution.author {
+            Some(a) => println!("{}: Attributed to {} (distance {:.4}) for {}", i, profiles.authors[a], attribution.distance, c.name),
+            None => println!("{}: None of the candidates (nearest {} at distance {:.4}) for {}", i, nearest, attribution.distance, c.name)
+        }
     }
-    // perceptron::test(model, &testing_set);
 }
 
 
+/// A CART tree and a random forest for Defoe against the rest, with the forest's
+/// out-of-bag accuracy and the permutation importance of each feature.
+fn corpus_forest(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats], rng: &mut StdRng) {
+    println!("\nGrowing a decision tree and a {}-tree random forest on {} positive samples, {} negative samples.", options.trees, pos.len(), neg.len());
+
+    let pipeline = fit_pipeline(options, neg.iter().chain(pos));
+    let training_set = pipeline.samples(neg.iter().chain(pos));
+    let classify_set = pipeline.samples(unknown);
+
+    let rows: Vec<usize> = (0..training_set.len()).collect();
+    let tree = match forest::grow(&training_set, &rows, options.depth, pipeline.names.len(), rng) {
+        Ok(tree) => tree,
+        Err(e) => {
+            println!("Skipping the decision tree and random forest: {}", e);
+            return;
+        }
+    };
+    println!("Decision tree: depth {}, {} leaves", tree.depth(), tree.leaves());
+    if let forest::Node::Split { feature, threshold, .. } = &tree {
+        println!("First split: {} <= {:.4}", pipeline.names[*feature], threshold);
+    }
+    for (i, (s, c)) in classify_set.iter().zip(unknown).enumerate() {
+        let (class, share) = tree.leaf(&s.values);
+        println!("{}: Sorted {} (leaf purity {:.3}) for {}", i, class, share, c.name);
+    }
+
+    let (model, in_bag) = match forest::Forest::fit(&training_set, options.trees, options.depth, rng) {
+        Ok(fitted) => fitted,
+        Err(e) => {
+            println!("Skipping the random forest: {}", e);
+            return;
+        }
+    };
+    println!("Random forest: out-of-bag accuracy {:.3}", model.out_of_bag_accuracy(&training_set, &in_bag));
+    let importances = model.importances(&training_set, &in_bag, rng);
+    let mut ranked: Vec<(&String, f64)> = pipeline.names.iter().zip(importances).collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    let top: Vec<String> = ranked.iter().take(15).map(|(name, drop)| format!("{} {:.3}", name, drop)).collect();
+    println!("Permutation importances (drop in out-of-bag accuracy): {}", top.join(", "));
+    for (i, (s, c)) in classify_set.iter().zip(unknown).enumerate() {
+        println!("{}: Sorted {} (vote margin {:.2}) for {}", i, model.predict(&s.values), model.score(&s.values), c.name);
+    }
+}
 
 
+/// Discriminant analysis over every author in the training corpus, reporting where
+/// each unknown book falls on the discriminant axes next to the authors' means.
+fn corpus_lda(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats]) {
+    let books: Vec<&CorpusStats> = neg.iter().chain(pos).collect();
+    let (authors, labels) = author_labels(books.iter().cloned());
+
+    let pipeline = fit_pipeline(options, books.iter().cloned());
+    let mut training_set = pipeline.samples(books.iter().cloned());
+    for (s, label) in training_set.iter_mut().zip(labels) {
+        s.class = label;
+    }
+    let model = match lda::Lda::fit(&training_set, options.ridge) {
+        Ok(model) => model,
+        Err(e) => {
+            println!("\nSkipping discriminant analysis: {}", e);
+            return;
+        }
+    };
+
+    let format_point = |point: &[f64]| point.iter().map(|x| format!("{:.3}", x)).collect::<Vec<String>>().join(", ");
+    let means: Vec<String> = model.classes.iter().zip(&model.centroids).map(|(a, c)| format!("{} ({})", authors[*a as usize], format_point(c))).collect();
+    let axes = if model.axes.len() == 1 { "1 axis".to_string() } else { format!("{} axes", model.axes.len()) };
+    println!("\nLinear discriminant analysis over {} authors ({}, ridge {}): author means at {}", authors.len(), axes, options.ridge, means.join(", "));
+    for (i, (s, c)) in pipeline.samples(unknown).iter().zip(unknown).enumerate() {
+        println!("{}: Sorted to {} (projected to {}) for {}", i, authors[model.predict(&s.values) as usize], format_point(&model.project(&s.values)), c.name);
+    }
+}
+
+
+/// The distance-based classifiers over every author in the training corpus:
+/// k-nearest-neighbours, which shows which training books each unknown one is
+/// closest to, and nearest shrunken centroids.
+fn corpus_neighbours(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats]) {
+    let books: Vec<&CorpusStats> = neg.iter().chain(pos).collect();
+    let (authors, labels) = author_labels(books.iter().cloned());
+
+    let pipeline = fit_pipeline(options, books.iter().cloned());
+    let mut training_set = pipeline.samples(books.iter().cloned());
+    for (s, label) in training_set.iter_mut().zip(labels) {
+        s.class = label;
+    }
+    let classify_set = pipeline.samples(unknown);
+
+    let knn = neighbours::Knn::fit(&training_set, options.neighbours, options.metric);
+    println!("\n{}-nearest-neighbours ({} distance) over {} books by {} authors.", knn.k, knn.metric, books.len(), authors.len());
+    for (i, (s, c)) in classify_set.iter().zip(unknown).enumerate() {
+        let nearest: Vec<String> = knn.neighbours(&s.values).iter().map(|(j, d)| format!("{} {:.4}", books[*j].name, d)).collect();
+        println!("{}: Sorted to {} (nearest: {}) for {}", i, authors[knn.predict(&s.values) as usize], nearest.join(", "), c.name);
+    }
+
+    let centroids = match neighbours::ShrunkenCentroids::fit(&training_set, options.shrinkage) {
+        Ok(model) => model,
+        Err(e) => {
+            println!("\nSkipping nearest shrunken centroids: {}", e);
+            return;
+        }
+    };
+    let surviving: Vec<&String> = pipeline.names.iter().zip(&centroids.used).filter(|(_, u)| **u).map(|(n, _)| n).collect();
+    println!("\nNearest shrunken centroids (shrinkage {}): {} of {} features survive", options.shrinkage, surviving.len(), pipeline.names.len());
+    for (i, (s, c)) in classify_set.iter().zip(unknown).enumerate() {
+        let discriminants = centroids.discriminants(&s.values);
+        let all: Vec<String> = centroids.classes.iter().zip(&discriminants).map(|(a, d)| format!("{} {:.4}", authors[*a as usize], d)).collect();
+        println!("{}: Sorted to {} (discriminants: {}) for {}", i, authors[centroids.predict(&s.values) as usize], all.join(", "), c.name);
+    }
+}
+
+
+/// Multinomial naive Bayes over every author in the training corpus, given unscaled
+/// word counts as `train` requires; skipped otherwise.
+fn corpus_naive_bayes(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats]) {
+    if let Err(e) = naive_bayes::check_features(&options.features, options.scaling) {
+        println!("\nSkipping naive Bayes: {}", e);
+        return;
+    }
+    let (authors, labels) = author_labels(neg.iter().chain(pos));
+    println!("\nTraining naive Bayes (smoothing {}, {} word counts) over {} authors.", options.alpha, options.selection, authors.len());
+
+    let pipeline = fit_pipeline(options, neg.iter().chain(pos));
+    let mut training_set = pipeline.samples(neg.iter().chain(pos));
+    for (s, label) in training_set.iter_mut().zip(labels) {
+        s.class = label;
+    }
+    let model = match naive_bayes::NaiveBayes::fit(&training_set, options.alpha) {
+        Ok(model) => model,
+        Err(e) => {
+            println!("Skipping naive Bayes: {}", e);
+            return;
+        }
+    };
+
+    for (i, (s, c)) in pipeline.samples(unknown).iter().zip(unknown).enumerate() {
+        let posteriors = model.log_posteriors(&s.values);
+        let best = model.predict(&s.values) as usize;
+        let all: Vec<String> = model.classes.iter().zip(&posteriors).map(|(a, p)| format!("{} {:.4}", authors[*a as usize], p)).collect();
+        println!("{}: Sorted to {} (log probabilities: {}) for {}", i, authors[best], all.join(", "), c.name)
+    }
+}
+
+
+/// Feature-free baseline: a PPM character model per training author, with each
+/// unknown book going to the author whose model compresses it into the fewest bits.
+fn corpus_ppm(options: &Options, pos: &[CorpusStats], neg: &[CorpusStats], unknown: &[CorpusStats]) {
+    let (authors, labels) = author_labels(neg.iter().chain(pos));
+    println!("\nTraining order-{} PPM models for {} authors.", options.ppm_order, authors.len());
+
+    let models: Vec<ppm::Ppm> = (0..authors.len()).map(|a| {
+        let texts: Vec<&str> = neg.iter().chain(pos).zip(&labels).filter(|(_, l)| **l as usize == a).map(|(b, _)| b.text.as_str()).collect();
+        ppm::Ppm::train(options.ppm_order, &texts)
+    }).collect();
+
+    for (i, c) in unknown.iter().enumerate() {
+        let bits: Vec<f64> = models.iter().map(|m| m.bits_per_char(&c.text)).collect();
+        let best = linalg::argmin(&bits);
+        let all: Vec<String> = authors.iter().zip(&bits).map(|(a, b)| format!("{} {:.4}", a, b)).collect();
+        println!("{}: Sorted to {} (bits per character: {}) for {}", i, authors[best], all.join(", "), c.name)
+    }
+}
+
+
+fn print_predictions(books: &[CorpusStats], classifications: &[i8], scores: &[f64], calibrator: Option<&Calibrator>) {
+    for (i, ((r, d), c)) in classifications.iter().zip(scores).zip(books).enumerate() {
+        match calibrator {
+            Some(calibrator) => println!("{}: Sorted {} (decision value {:.4}, probability of Defoe {:.3}) for {}", i, r, d, calibrator.probability(*d), c.name),
+            None => println!("{}: Sorted {} (decision value {:.4}) for {}", i, r, d, c.name)
+        }
+    }
+}
+
+
+/// The pipeline `options` ask for, fitted to the training books; exits if there are none.
+fn fit_pipeline<'a, I: IntoIterator<Item = &'a CorpusStats>>(options: &Options, training: I) -> Pipeline {
+    Pipeline::fit(&options.features, options.selection, options.scaling, training).unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+
+fn load_corpus(pattern: &str, class: u8, binning: &Binning) -> Vec<CorpusStats> {
+    let mut books = Vec::new();
+    for path in glob(pattern).expect("Failed to read glob pattern").flatten() {
+        let corpus_data = read_book(&path, class);
+        let corpus_stats = statistics(corpus_data, binning);
+        println!("{:}", corpus_stats);
+        books.push(corpus_stats);
+    }
+    books
+}
+
+
+/// `train <model file>`: fit a pipeline and model on the whole labelled corpus and save them.
+fn train_command(model_path: &str, options: &Options) {
+    let binning = Binning::default();
+    let defoe_vecs = load_corpus("data/corpus/defoe_*.txt", 1, &binning);
+    let other_vecs = load_corpus("data/corpus/[!defoe_|unknown_]*.txt", 0, &binning);
+
+    let pipeline = fit_pipeline(options, other_vecs.iter().chain(&defoe_vecs));
+    let training_set = pipeline.samples(other_vecs.iter().chain(&defoe_vecs));
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let model = Model::train(&options.model, &training_set, options, &mut rng, true).unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
+    });
+    let calibrator = fit_calibrator(&options.model, options, &defoe_vecs, &other_vecs, &mut rng);
+
+    let saved = SavedModel { seed: options.seed, binning, pipeline, model, calibrator };
+    match persist::save(model_path, &saved) {
+        Ok(()) => println!("\nSaved {} model over {} features (seed {}) to {}", saved.model.name(), saved.pipeline.names.len(), saved.seed, model_path),
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+
+/// `verify [book ...]`: a one-class SVM fitted to the defoe_* books alone, which
+/// accepts or rejects each of the given books (default: unknown_*.txt) as Defoe's
+/// without needing a pool of other authors.
+fn verify_command(options: &Options, books: &[String]) {
+    let binning = Binning::default();
+    let defoe_vecs = load_corpus("data/corpus/defoe_*.txt", 1, &binning);
+    let unknown_vecs: Vec<CorpusStats> = if books.is_empty() {
+        load_corpus("data/corpus/unknown_*.txt", 0, &binning)
+    } else {
+        books.iter().map(|b| statistics(read_book(Path::new(b), 0), &binning)).collect()
+    };
+
+    let pipeline = fit_pipeline(options, &defoe_vecs);
+    let training_set = pipeline.samples(&defoe_vecs);
+    // a linear one-class boundary is a half-space, which says little about a single author
+    let kernel = match options.kernel {
+        Kernel::Linear => Kernel::Rbf { gamma: 1.0 / pipeline.names.len() as f64 },
+        other => other
+    };
+    let model = svm::train_one_class(&training_set, options.nu, kernel);
+    let accepted = training_set.iter().filter(|s| model.inside(&s.values)).count();
+    println!("\nVerifying against {} Defoe books (seed {}): one-class SVM (nu {}, {} kernel) with {} support vectors accepts {}/{} of them",
+        defoe_vecs.len(), options.seed, options.nu, model.kernel, model.support.len(), accepted, training_set.len());
+
+    for (i, (s, c)) in pipeline.samples(&unknown_vecs).iter().zip(&unknown_vecs).enumerate() {
+        let score = model.decision(&s.values);
+        let verdict = if model.inside(&s.values) { "Accepted as Defoe" } else { "Rejected as not Defoe" };
+        println!("{}: {} (score {:.4}) for {}", i, verdict, score, c.name)
+    }
+}
+
+
+/// `impostors [book ...]`: General Impostors verification of each of the given books
+/// (default: unknown_*.txt) as Defoe's, with the other authors' books as impostors.
+fn impostors_command(options: &Options, books: &[String], rng: &mut StdRng) {
+    let binning = Binning::default();
+    let defoe_vecs = load_corpus("data/corpus/defoe_*.txt", 1, &binning);
+    let other_vecs = load_corpus("data/corpus/[!defoe_|unknown_]*.txt", 0, &binning);
+    let unknown_vecs: Vec<CorpusStats> = if books.is_empty() {
+        load_corpus("data/corpus/unknown_*.txt", 0, &binning)
+    } else {
+        books.iter().map(|b| statistics(read_book(Path::new(b), 0), &binning)).collect()
+    };
+
+    let pipeline = fit_pipeline(options, other_vecs.iter().chain(&defoe_vecs));
+    let candidate: Vec<Vec<f64>> = pipeline.samples(&defoe_vecs).into_iter().map(|s| s.values).collect();
+    let impostors: Vec<Vec<f64>> = pipeline.samples(&other_vecs).into_iter().map(|s| s.values).collect();
+    let columns: Vec<usize> = (0..pipeline.names.len()).filter(|j| pipeline.names[*j] != "bias").collect();
+
+    println!("\nGeneral Impostors: {} Defoe books against {} impostors over {} features, {} iterations (seed {})",
+        candidate.len(), impostors.len(), columns.len(), options.iterations, options.seed);
+    for (i, (s, c)) in pipeline.samples(&unknown_vecs).iter().zip(&unknown_vecs).enumerate() {
+        let score = impostors::score(&s.values, &candidate, &impostors, &columns, options.iterations, rng);
+        println!("{}: Defoe closest in {:.1}% of iterations for {}", i, 100.0 * score, c.name)
+    }
+}
+
+
+/// Relative word frequencies in consecutive chunks of `words` words.
+fn read_chunks(path: &Path, words: usize) -> Vec<HashMap<String, f64>> {
+    let text = std::fs::read_to_string(path).expect("No such file");
+    let tokens: Vec<String> = text.split_whitespace().map(|w| nlp::normalise(w).0).filter(|w| !w.is_empty()).collect();
+    tokens.chunks_exact(words).map(|chunk| {
+        let mut frequencies = HashMap::new();
+        for w in chunk {
+            *frequencies.entry(w.clone()).or_insert(0.0) += 1.0 / words as f64;
+        }
+        frequencies
+    }).collect()
+}
+
+
+/// Unmasking curve of one text against a pool of another's chunks, each side cut down
+/// to at most `unmasking::MAX_CHUNKS` random chunks.
+fn unmasking_curve(text: &[HashMap<String, f64>], pool: &[&HashMap<String, f64>], rng: &mut StdRng) -> Vec<f64> {
+    let a: Vec<&HashMap<String, f64>> = rand::seq::index::sample(rng, text.len(), text.len().min(unmasking::MAX_CHUNKS))
+        .iter().map(|i| &text[i]).collect();
+    let b: Vec<&HashMap<String, f64>> = rand::seq::index::sample(rng, pool.len(), pool.len().min(unmasking::MAX_CHUNKS))
+        .iter().map(|i| pool[i]).collect();
+    let vocabulary = unmasking::vocabulary(&[a.clone(), b.clone()].concat(), unmasking::VOCABULARY);
+    let vectors = |chunks: &[&HashMap<String, f64>]| -> Vec<Vec<f64>> { chunks.iter().map(|c| unmasking::vector(c, &vocabulary)).collect() };
+    unmasking::curve(&vectors(&a), &vectors(&b), rng)
+}
+
+
+/// `unmask [book ...]`: unmasking curves of each of the given books (default:
+/// unknown_*.txt) against Defoe. Curves of each Defoe book against the rest of Defoe
+/// (same author) and of each other book against Defoe (different authors) train a
+/// meta-classifier that reads the questioned curves. All curves are written to
+/// `<output>.csv` and plotted in `<output>.svg`.
+fn unmask_command(options: &Options, books: &[String], rng: &mut StdRng) {
+    let chunked = |pattern: &str| -> Vec<(String, Vec<HashMap<String, f64>>)> {
+        let paths: Vec<PathBuf> = glob(pattern).expect("Failed to read glob pattern").flatten().collect();
+        paths.iter().map(|p| (p.file_name().unwrap().to_string_lossy().to_string(), read_chunks(p, unmasking::CHUNK_WORDS))).collect()
+    };
+    let defoe = chunked("data/corpus/defoe_*.txt");
+    let others = chunked("data/corpus/[!defoe_|unknown_]*.txt");
+    let unknown: Vec<(String, Vec<HashMap<String, f64>>)> = if books.is_empty() {
+        chunked("data/corpus/unknown_*.txt")
+    } else {
+        books.iter().map(|b| (b.clone(), read_chunks(Path::new(b), unmasking::CHUNK_WORDS))).collect()
+    };
+    for (name, chunks) in defoe.iter().chain(&others).chain(&unknown) {
+        if chunks.is_empty() {
+            println!("{} is shorter than one {}-word chunk", name, unmasking::CHUNK_WORDS);
+            std::process::exit(1);
+        }
+    }
+    println!("\nUnmasking against {} Defoe books ({}-word chunks, {} most frequent words, seed {})", defoe.len(), unmasking::CHUNK_WORDS, unmasking::VOCABULARY, options.seed);
+
+    let pool = |skip: Option<usize>| -> Vec<&HashMap<String, f64>> {
+        defoe.iter().enumerate().filter(|(i, _)| Some(*i) != skip).flat_map(|(_, (_, chunks))| chunks).collect()
+    };
+    // (kind, book, curve)
+    let mut curves: Vec<(&str, &str, Vec<f64>)> = Vec::new();
+    if defoe.len() > 1 {
+        for (i, (name, chunks)) in defoe.iter().enumerate() {
+            curves.push(("same", name, unmasking_curve(chunks, &pool(Some(i)), rng)));
+        }
+    }
+    for (name, chunks) in &others {
+        curves.push(("different", name, unmasking_curve(chunks, &pool(None), rng)));
+    }
+    for (name, chunks) in &unknown {
+        curves.push(("questioned", name, unmasking_curve(chunks, &pool(None), rng)));
+    }
+    for (kind, name, curve) in &curves {
+        let points: Vec<String> = curve.iter().map(|a| format!("{:.2}", a)).collect();
+        println!("{} ({}): {}", name, kind, points.join(" "));
+    }
+
+    let same: Vec<Vec<f64>> = curves.iter().filter(|c| c.0 == "same").map(|c| c.2.clone()).collect();
+    let different: Vec<Vec<f64>> = curves.iter().filter(|c| c.0 == "different").map(|c| c.2.clone()).collect();
+    if same.is_empty() || different.is_empty() {
+        println!("Need at least two Defoe books and one other book to train the meta-classifier");
+    } else {
+        let meta = unmasking::MetaClassifier::fit(&same, &different);
+        for (i, (_, name, curve)) in curves.iter().filter(|c| c.0 == "questioned").enumerate() {
+            let decision = meta.decision(curve);
+            let verdict = if decision > 0.0 { "Same author as Defoe" } else { "Different author from Defoe" };
+            println!("{}: {} (meta decision value {:.4}) for {}", i, verdict, decision, name)
+        }
+    }
+
+    let mut csv = String::from("book,kind");
+    for round in 0..=unmasking::ROUNDS {
+        csv.push_str(&format!(",round{}", round));
+    }
+    csv.push('\n');
+    let mut plot = svg::Plot::new("Unmasking against Defoe", "Round of feature elimination", "Cross-validated accuracy", (0.0, unmasking::ROUNDS as f64), (0.0, 1.0));
+    plot.describe(&format!("seed {}", options.seed));
+    for (k, kind) in ["same", "different", "questioned"].iter().enumerate() {
+        plot.legend(kind, svg::PALETTE[k]);
+        for (_, name, curve) in curves.iter().filter(|c| c.0 == *kind) {
+            let values: Vec<String> = curve.iter().map(|a| a.to_string()).collect();
+            csv.push_str(&format!("{},{},{}\n", name, kind, values.join(",")));
+            let points: Vec<(f64, f64)> = curve.iter().enumerate().map(|(r, a)| (r as f64, *a)).collect();
+            plot.line(&points, svg::PALETTE[k]);
+        }
+    }
+    let output = options.output.as_deref().unwrap_or("unmasking");
+    let csv_path = format!("{}.csv", output);
+    let svg_path = format!("{}.svg", output);
+    let written = std::fs::write(&csv_path, csv).map_err(|e| format!("Could not write {}: {}", csv_path, e))
+        .and_then(|_| plot.save(&svg_path));
+    match written {
+        Ok(()) => println!("Wrote curves to {} and {}", csv_path, svg_path),
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+
+/// `pca [book ...]`: every corpus book, plus the given ones, projected onto the first
+/// two principal components of their features, printed and plotted in `<output>.svg`
+/// with each book coloured by author.
+fn pca_command(options: &Options, books: &[String]) {
+    let binning = Binning::default();
+    let mut corpus = load_corpus("data/corpus/*.txt", 0, &binning);
+    corpus.extend(books.iter().map(|b| statistics(read_book(Path::new(b), 0), &binning)));
+
+    let pipeline = fit_pipeline(options, &corpus);
+    let rows: Vec<Vec<f64>> = pipeline.samples(&corpus).into_iter().map(|s| s.values).collect();
+    let model = pca::Pca::fit(&rows, 2);
+    if model.components.len() < 2 {
+        println!("Need at least two principal components, but the features only give {}", model.components.len());
+        std::process::exit(1);
+    }
+
+    println!("\nPrincipal components of {} books over {} features (seed {})", corpus.len(), pipeline.names.len(), options.seed);
+    let shares: Vec<f64> = model.variances.iter().map(|v| 100.0 * v / model.total).collect();
+    for (c, (component, share)) in model.components.iter().zip(&shares).enumerate() {
+        let mut loadings: Vec<(&String, f64)> = pipeline.names.iter().zip(component.iter().cloned()).collect();
+        loadings.sort_by(|a, b| b.1.abs().partial_cmp(&a.1.abs()).unwrap());
+        let top: Vec<String> = loadings.iter().take(8).map(|(name, w)| format!("{} {:+.3}", name, w)).collect();
+        println!("PC{} ({:.1}% of the variance): {}", c + 1, share, top.join(", "));
+    }
+
+    let points: Vec<Vec<f64>> = rows.iter().map(|r| model.project(r)).collect();
+    let range = |c: usize| {
+        let (low, high) = points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), p| (l.min(p[c]), h.max(p[c])));
+        let pad = 0.1 * (high - low);
+        (low - pad, high + pad)
+    };
+    let mut plot = svg::Plot::new("Principal components of the corpus", &format!("PC1 ({:.1}%)", shares[0]), &format!("PC2 ({:.1}%)", shares[1]), range(0), range(1));
+    plot.describe(&format!("seed {}", options.seed));
+    let (authors, labels) = author_labels(&corpus);
+    let colours = svg::colours(authors.len());
+    for (name, colour) in authors.iter().zip(&colours) {
+        plot.legend(name, colour);
+    }
+    for ((book, point), a) in corpus.iter().zip(&points).zip(labels) {
+        let stem = book.name.trim_end_matches(".txt");
+        println!("{}: ({:.4}, {:.4})", book.name, point[0], point[1]);
+        plot.point(point[0], point[1], &colours[a as usize], &book.name, Some(stem));
+    }
+
+    let svg_path = format!("{}.svg", options.output.as_deref().unwrap_or("pca"));
+    match plot.save(&svg_path) {
+        Ok(()) => println!("Wrote the plot to {}", svg_path),
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+
+/// `classify <model file> [book ...]`: apply a saved model to the given books, or to
+/// the unknown_* books if none are given.
+fn classify_command(model_path: &str, books: &[String]) {
+    let saved = persist::load(model_path).unwrap_or_else(|e| {
+        println!("{}", e);
+        std::process::exit(1);
+    });
+
+    let unknown_vecs: Vec<CorpusStats> = if books.is_empty() {
+        load_corpus("data/corpus/unknown_*.txt", 0, &saved.binning)
+    } else {
+        books.iter().map(|b| statistics(read_book(Path::new(b), 0), &saved.binning)).collect()
+    };
+
+    println!("\nClassifying with {} model from {} ({} features, trained with seed {})", saved.model.name(), model_path, saved.pipeline.names.len(), saved.seed);
+    let samples = saved.pipeline.samples(&unknown_vecs);
+    print_predictions(&unknown_vecs, &saved.model.classify(&samples), &saved.model.scores(&samples), saved.calibrator.as_ref());
+}
+
 
 fn main() {
-    let mut rng = rand::thread_rng();
+    let args: Vec<String> = std::env::args().collect();
+    let options = Options::parse(&args);
+    println!("Random seed: {}", options.seed);
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    let positional = positional(&args);
+    match positional.first().map(|c| c.as_str()) {
+        Some("train") | Some("classify") if positional.len() < 2 => {
+            println!("usage: {} train <model file> | classify <model file> [book ...] | verify [book ...] | impostors [book ...] | unmask [book ...] | pca [book ...]", args[0]);
+            std::process::exit(1);
+        },
+        Some("train") => {
+            train_command(&positional[1], &options);
+            return;
+        },
+        Some("classify") => {
+            classify_command(&positional[1], &positional[2..]);
+            return;
+        },
+        Some("verify") => {
+            verify_command(&options, &positional[1..]);
+            return;
+        },
+        Some("impostors") => {
+            impostors_command(&options, &positional[1..], &mut rng);
+            return;
+        },
+        Some("unmask") => {
+            unmask_command(&options, &positional[1..], &mut rng);
+            return;
+        },
+        Some("pca") => {
+            pca_command(&options, &positional[1..]);
+            return;
+        },
+        Some(other) => {
+            println!("Unknown command `{}` (expected train, classify, verify, impostors, unmask or pca)", other);
+            std::process::exit(1);
+        },
+        None => ()
+    }
 
     let irises = read_iris_data();
-    for iris_specices in ["Iris-setosa", "Iris-versicolor", "Iris-virginica"].iter() {
-        iris_perceptron(&irises, iris_specices, 0.1333);
+    let species = ["Iris-setosa", "Iris-versicolor", "Iris-virginica"];
+    for iris_specices in species.iter() {
+        iris_perceptron(&irises, iris_specices, 0.1333, &options.perceptron, &mut rng);
     }
+    iris_multiclass(&irises, &species, 0.1333, &options.perceptron, &mut rng);
+    iris_mlp(&irises, &species, 0.1333, &options.mlp, &mut rng);
+    iris_lda(&irises, &species, 0.1333, options.ridge, &mut rng);
 
     
     
     // let mut other_vecs = vec![vec![0.05, 0.91], vec![0.09, 0.86], vec![0.15, 0.97], vec![0.10, 0.76], vec![0.00, 0.99]]; // = -1
     
-    // svm::train(defoe_vecs, other_vecs);
 
     // let v3 = dot(&v1, &v2);
-    // let v4 = norm(&v1);
     // println!("dot prod is {:?}", v3);
     // println!("the norm is {:?}", v4);
 
 
-    let mut defoe_vecs = Vec::new();
-    for entry in glob("data/corpus/defoe_*.txt").expect("Failed to read glob pattern") {
-        if let Ok(path) = entry {
-            let f = path.file_name().unwrap().to_str().unwrap();
-            let corpus_data = read_book(f, 1);
-            let corpus_stats = statistics(corpus_data);
-            println!("{:}", corpus_stats);
-            defoe_vecs.push(corpus_stats);
-        }
+    let binning = Binning::default();
+    let defoe_vecs = load_corpus("data/corpus/defoe_*.txt", 1, &binning);
+    let other_vecs = load_corpus("data/corpus/[!defoe_|unknown_]*.txt", 0, &binning);
+    let unknown_vecs = load_corpus("data/corpus/unknown_*.txt", 0, &binning);
+
+    corpus_perceptron(&options, &defoe_vecs, &other_vecs, &unknown_vecs, &mut rng);
+    corpus_svm(&options, &defoe_vecs, &other_vecs, &unknown_vecs, &mut rng);
+    corpus_logistic(&options, &defoe_vecs, &other_vecs, &unknown_vecs);
+    corpus_forest(&options, &defoe_vecs, &other_vecs, &unknown_vecs, &mut rng);
+    corpus_lda(&options, &defoe_vecs, &other_vecs, &unknown_vecs);
+    corpus_neighbours(&options, &defoe_vecs, &other_vecs, &unknown_vecs);
+    corpus_naive_bayes(&options, &defoe_vecs, &other_vecs, &unknown_vecs);
+    corpus_ppm(&options, &defoe_vecs, &other_vecs, &unknown_vecs);
+    if let Some(coverage) = options.open_set {
+        corpus_open_set(&options, coverage, &defoe_vecs, &other_vecs, &unknown_vecs);
     }
     
-    let mut other_vecs = Vec::new();
-    for entry in glob("data/corpus/[!defoe_|unknown_]*.txt").expect("Failed to read glob pattern") {
-        if let Ok(path) = entry {
-            let f = path.file_name().unwrap().to_str().unwrap();
-            let corpus_data = read_book(f, 0);
-            let corpus_stats = statistics(corpus_data);
-            println!("{:}", corpus_stats);
-            other_vecs.push(corpus_stats);
-        }
-    }
-    
-    let mut unknown_vecs = Vec::new();
-    for entry in glob("data/corpus/unknown_*.txt").expect("Failed to read glob pattern") {
-        if let Ok(path) = entry {
-            let f = path.file_name().unwrap().to_str().unwrap();
-            let corpus_data = read_book(f, 0);
-            let corpus_stats = statistics(corpus_data);
-            println!("{:}", corpus_stats);
-            unknown_vecs.push(corpus_stats);
-        }
-    }
-
-    corpus_perceptron(&defoe_vecs, &other_vecs, &unknown_vecs);
-    
     std::process::exit(0);
 
 
     // println!("{:?}", word_frequency);
 }
-
@@ -0,0 +1,310 @@
+use std::fmt;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use crate::linalg;
+use crate::perceptron::Sample;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+    Relu,
+    Tanh,
+    Sigmoid
+}
+
+impl Activation {
+    pub fn parse(name: &str) -> Result<Activation, String> {
+        match name {
+            "relu" => Ok(Activation::Relu),
+            "tanh" => Ok(Activation::Tanh),
+            "sigmoid" => Ok(Activation::Sigmoid),
+            other => Err(format!("Unknown activation `{}` (expected relu, tanh or sigmoid)", other))
+        }
+    }
+
+    fn apply(&self, z: f64) -> f64 {
+        match self {
+            Activation::Relu => z.max(0.0),
+            Activation::Tanh => z.tanh(),
+            Activation::Sigmoid => 1.0 / (1.0 + (-z).exp())
+        }
+    }
+
+    /// The derivative, in terms of the activation's output `a`.
+    fn slope(&self, a: f64) -> f64 {
+        match self {
+            Activation::Relu => if a > 0.0 { 1.0 } else { 0.0 },
+            Activation::Tanh => 1.0 - a * a,
+            Activation::Sigmoid => a * (1.0 - a)
+        }
+    }
+}
+
+/// The name accepted by `Activation::parse`.
+impl fmt::Display for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Activation::Relu => "relu",
+            Activation::Tanh => "tanh",
+            Activation::Sigmoid => "sigmoid"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct MlpConfig {
+    /// Width of each hidden layer, input side first.
+    pub hidden: Vec<usize>,
+    pub activation: Activation,
+    pub rate: f64,
+    pub epochs: usize,
+    pub batch: usize,
+    pub momentum: f64,
+    /// L2 weight decay; biases aren't decayed.
+    pub decay: f64
+}
+
+impl Default for MlpConfig {
+    fn default() -> MlpConfig {
+        MlpConfig {
+            hidden: vec![16],
+            activation: Activation::Relu,
+            rate: 0.05,
+            epochs: 200,
+            batch: 8,
+            momentum: 0.9,
+            decay: 1e-4
+        }
+    }
+}
+
+
+/// A fully connected layer: `weights[o][i]` connects input i to output o.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub weights: Vec<Vec<f64>>,
+    pub bias: Vec<f64>
+}
+
+impl Layer {
+    /// A layer of the same shape with every weight and bias zero.
+    fn zeros(&self) -> Layer {
+        Layer {
+            weights: self.weights.iter().map(|row| vec![0.0; row.len()]).collect(),
+            bias: vec![0.0; self.bias.len()]
+        }
+    }
+
+    fn forward(&self, input: &[f64]) -> Vec<f64> {
+        self.weights.iter().zip(&self.bias)
+            .map(|(row, b)| b + row.iter().zip(input).map(|(w, x)| w * x).sum::<f64>())
+            .collect()
+    }
+}
+
+
+/// A feed-forward network with a softmax output over `classes`, trained on cross-entropy
+/// by mini-batch gradient descent with momentum and weight decay.
+#[derive(Debug, Clone)]
+pub struct Mlp {
+    pub activation: Activation,
+    pub classes: Vec<i8>,
+    pub layers: Vec<Layer>,
+    /// Mean cross-entropy over the training samples, per epoch.
+    pub epoch_losses: Vec<f64>
+}
+
+impl Mlp {
+    pub fn train(samples: &[Sample], config: &MlpConfig, rng: &mut StdRng) -> Mlp {
+        let mut classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+        classes.sort_unstable();
+        classes.dedup();
+
+        // Glorot uniform initialisation keeps the activations' spread steady from layer to layer
+        let sizes: Vec<usize> = [samples[0].values.len()].iter().chain(&config.hidden).chain(&[classes.len()]).cloned().collect();
+        let mut layers: Vec<Layer> = sizes.windows(2).map(|io| {
+            let limit = (6.0 / (io[0] + io[1]) as f64).sqrt();
+            Layer {
+                weights: (0..io[1]).map(|_| (0..io[0]).map(|_| rng.gen_range(-limit..limit)).collect()).collect(),
+                bias: vec![0.0; io[1]]
+            }
+        }).collect();
+        let mut velocity: Vec<Layer> = layers.iter().map(Layer::zeros).collect();
+
+        let mut mlp = Mlp { activation: config.activation, classes, layers: Vec::new(), epoch_losses: Vec::new() };
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        for _epoch in 0..config.epochs {
+            order.shuffle(rng);
+            let mut loss = 0.0;
+            for batch in order.chunks(config.batch.max(1)) {
+                let mut gradient: Vec<Layer> = layers.iter().map(Layer::zeros).collect();
+                for s in batch.iter().map(|i| &samples[*i]) {
+                    let target = mlp.classes.iter().position(|c| *c == s.class).unwrap();
+                    loss += mlp.backpropagate(&layers, &s.values, target, &mut gradient);
+                }
+
+                let n = batch.len() as f64;
+                for ((layer, v), g) in layers.iter_mut().zip(velocity.iter_mut()).zip(&gradient) {
+                    for o in 0..layer.weights.len() {
+                        for i in 0..layer.weights[o].len() {
+                            let step = g.weights[o][i] / n + config.decay * layer.weights[o][i];
+                            v.weights[o][i] = config.momentum * v.weights[o][i] - config.rate * step;
+                            layer.weights[o][i] += v.weights[o][i];
+                        }
+                        v.bias[o] = config.momentum * v.bias[o] - config.rate * g.bias[o] / n;
+                        layer.bias[o] += v.bias[o];
+                    }
+                }
+            }
+            mlp.epoch_losses.push(loss / samples.len() as f64);
+        }
+        mlp.layers = layers;
+        mlp
+    }
+
+    /// Every layer's output, the input first and the softmax probabilities last.
+    fn forward(&self, layers: &[Layer], values: &[f64]) -> Vec<Vec<f64>> {
+        let mut outputs = vec![values.to_vec()];
+        for (l, layer) in layers.iter().enumerate() {
+            let z = layer.forward(outputs.last().unwrap());
+            outputs.push(if l + 1 == layers.len() { softmax(&z) } else { z.iter().map(|z| self.activation.apply(*z)).collect() });
+        }
+        outputs
+    }
+
+    /// Adds the cross-entropy gradient for one sample to `gradient` and returns its loss.
+    fn backpropagate(&self, layers: &[Layer], values: &[f64], target: usize, gradient: &mut [Layer]) -> f64 {
+        let outputs = self.forward(layers, values);
+        let probabilities = outputs.last().unwrap();
+        let loss = -probabilities[target].max(1e-300).ln();
+
+        // softmax with cross-entropy: the error at the logits is p - onehot
+        let mut delta: Vec<f64> = probabilities.iter().enumerate().map(|(c, p)| p - (c == target) as u8 as f64).collect();
+        for l in (0..layers.len()).rev() {
+            let input = &outputs[l];
+            for (o, d) in delta.iter().enumerate() {
+                gradient[l].bias[o] += d;
+                for (g, x) in gradient[l].weights[o].iter_mut().zip(input) {
+                    *g += d * x;
+                }
+            }
+            if l > 0 {
+                delta = (0..input.len())
+                    .map(|i| self.activation.slope(input[i]) * delta.iter().enumerate().map(|(o, d)| d * layers[l].weights[o][i]).sum::<f64>())
+                    .collect();
+            }
+        }
+        loss
+    }
+
+    /// Probability of each class in `classes`.
+    pub fn probabilities(&self, values: &[f64]) -> Vec<f64> {
+        self.forward(&self.layers, values).pop().unwrap()
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        self.classes[linalg::argmax(&self.probabilities(values))]
+    }
+
+    /// Log odds of `class` against all the others; positive when it is the more likely.
+    pub fn log_odds(&self, values: &[f64], class: i8) -> f64 {
+        let probabilities = self.probabilities(values);
+        match self.classes.iter().position(|c| *c == class) {
+            Some(c) => probabilities[c].max(1e-300).ln() - (1.0 - probabilities[c]).max(1e-300).ln(),
+            None => f64::NEG_INFINITY
+        }
+    }
+
+    pub fn classify(&self, samples: &[Sample]) -> Vec<i8> {
+        samples.iter().map(|s| self.predict(&s.values)).collect()
+    }
+}
+
+impl fmt::Display for Mlp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sizes: Vec<String> = self.layers.iter().map(|l| l.weights.first().map_or(0, |row| row.len()).to_string())
+            .chain(self.layers.last().map(|l| l.weights.len().to_string()))
+            .collect();
+        write!(f, "Multi-layer perceptron {} ({}): ", sizes.join("-"), self.activation)?;
+        match (self.epoch_losses.first(), self.epoch_losses.last()) {
+            (Some(first), Some(last)) => write!(f, "cross-entropy {:.4} after the first epoch, {:.4} after {}", first, last, self.epoch_losses.len()),
+            _ => write!(f, "no training history")
+        }
+    }
+}
+
+
+/// Probabilities from logits, shifted by the largest so `exp` can't overflow.
+fn softmax(z: &[f64]) -> Vec<f64> {
+    let max = z.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = z.iter().map(|z| (z - max).exp()).collect();
+    let total: f64 = exps.iter().sum();
+    exps.iter().map(|e| e / total).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+
+    fn config(hidden: Vec<usize>) -> MlpConfig {
+        MlpConfig { hidden, activation: Activation::Tanh, ..MlpConfig::default() }
+    }
+
+    #[test]
+    fn softmax_is_a_distribution_even_for_huge_logits() {
+        let p = softmax(&[1000.0, 1000.0, 0.0]);
+        assert!((p.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((p[0] - 0.5).abs() < 1e-12 && p[2] < 1e-300);
+    }
+
+    /// Random weights for a network shaped like `mlp`'s, so no gradient is zero.
+    fn random_layers(mlp: &Mlp, rng: &mut StdRng) -> Vec<Layer> {
+        mlp.layers.iter().map(|layer| Layer {
+            weights: layer.weights.iter().map(|row| row.iter().map(|_| rng.gen_range(-1.0..1.0)).collect()).collect(),
+            bias: layer.bias.iter().map(|_| rng.gen_range(-1.0..1.0)).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn backpropagation_matches_numerical_gradients_through_uneven_layers() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let samples: Vec<Sample> = (0..3).map(|c| Sample { values: vec![0.5 * c as f64, 1.0 - 0.3 * c as f64], class: c }).collect();
+        let mlp = Mlp::train(&samples, &MlpConfig { epochs: 0, ..config(vec![4, 3]) }, &mut rng);
+        let layers: Vec<Layer> = random_layers(&mlp, &mut rng);
+
+        let (values, target) = (vec![0.3, -0.7], 2);
+        let mut gradient: Vec<Layer> = layers.iter().map(Layer::zeros).collect();
+        mlp.backpropagate(&layers, &values, target, &mut gradient);
+
+        let loss = |layers: &[Layer]| -mlp.forward(layers, &values).last().unwrap()[target].ln();
+        let h = 1e-6;
+        for l in 0..layers.len() {
+            for o in 0..layers[l].weights.len() {
+                for i in 0..layers[l].weights[o].len() {
+                    let (mut up, mut down) = (layers.clone(), layers.clone());
+                    up[l].weights[o][i] += h;
+                    down[l].weights[o][i] -= h;
+                    let numerical = (loss(&up) - loss(&down)) / (2.0 * h);
+                    assert!((gradient[l].weights[o][i] - numerical).abs() < 1e-6, "layer {} weight {} {}", l, o, i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_hidden_layer_learns_xor() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let xor: Vec<Sample> = [(0.0, 0.0, 0), (0.0, 1.0, 1), (1.0, 0.0, 1), (1.0, 1.0, 0)].iter()
+            .map(|(x, y, class)| Sample { values: vec![*x, *y], class: *class })
+            .collect();
+        let mlp = Mlp::train(&xor, &MlpConfig { epochs: 500, batch: 4, rate: 0.1, ..config(vec![8]) }, &mut rng);
+        assert_eq!(mlp.classify(&xor), vec![0, 1, 1, 0]);
+        assert!(mlp.epoch_losses.last().unwrap() < &mlp.epoch_losses[0]);
+        assert!(mlp.log_odds(&[0.0, 1.0], 1) > 0.0);
+    }
+}
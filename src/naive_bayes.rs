@@ -0,0 +1,146 @@
+use crate::features::FeatureSet;
+use crate::linalg;
+use crate::perceptron::Sample;
+use crate::scaling::Scaling;
+
+
+/// Multinomial naive Bayes over feature values treated as counts, e.g. the
+/// `word_counts` features with no scaling. Any number of classes.
+#[derive(Debug, Clone)]
+pub struct NaiveBayes {
+    pub classes: Vec<i8>,
+    pub log_priors: Vec<f64>,
+    /// `log_likelihoods[c][j]`: log probability of a unit of feature j under class c.
+    pub log_likelihoods: Vec<Vec<f64>>,
+    /// Features that vary over the training samples. A constant column such as `bias`
+    /// says nothing about the class but would still tilt the likelihoods, so it is left out.
+    pub used: Vec<bool>
+}
+
+/// The multinomial likelihoods only make sense over raw counts, so naive Bayes is
+/// refused any features but unscaled `word_counts`.
+pub fn check_features(features: &FeatureSet, scaling: Scaling) -> Result<(), String> {
+    if features.fields != ["word_counts"] || scaling != Scaling::None {
+        return Err("Naive Bayes needs raw word counts: use --features word_counts --scaling none".to_string());
+    }
+    Ok(())
+}
+
+
+impl NaiveBayes {
+    /// Lidstone smoothing adds `alpha` to every count (Laplace smoothing is `alpha = 1`).
+    pub fn fit(samples: &[Sample], alpha: f64) -> Result<NaiveBayes, String> {
+        if alpha <= 0.0 {
+            return Err(format!("Smoothing must be positive, not {}", alpha));
+        }
+        if samples.iter().flat_map(|s| &s.values).any(|x| *x < 0.0) {
+            return Err("Naive Bayes needs non-negative counts; try `--features word_counts --scaling none`".to_string());
+        }
+        let mut classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+        classes.sort_unstable();
+        classes.dedup();
+
+        let dims = samples[0].values.len();
+        let used: Vec<bool> = (0..dims).map(|j| samples.iter().any(|s| s.values[j] != samples[0].values[j])).collect();
+
+        let mut log_priors = Vec::new();
+        let mut log_likelihoods = Vec::new();
+        for class in &classes {
+            let members: Vec<&Sample> = samples.iter().filter(|s| s.class == *class).collect();
+            log_priors.push((members.len() as f64 / samples.len() as f64).ln());
+
+            let counts: Vec<f64> = (0..dims).map(|j| if used[j] { members.iter().map(|s| s.values[j]).sum::<f64>() + alpha } else { 0.0 }).collect();
+            let total: f64 = counts.iter().sum();
+            // unused columns are skipped when scoring, so what they hold doesn't matter
+            log_likelihoods.push(counts.iter().map(|c| if *c > 0.0 { (c / total).ln() } else { 0.0 }).collect());
+        }
+
+        Ok(NaiveBayes { classes, log_priors, log_likelihoods, used })
+    }
+
+    /// Log prior plus log likelihood of `values` for each class in `classes`.
+    fn joint(&self, values: &[f64]) -> Vec<f64> {
+        self.log_priors.iter().zip(&self.log_likelihoods)
+            .map(|(prior, likelihoods)| {
+                prior + values.iter().zip(likelihoods).zip(&self.used)
+                    .filter(|((x, _), used)| **used && **x > 0.0)
+                    .map(|((x, l), _)| x * l)
+                    .sum::<f64>()
+            })
+            .collect()
+    }
+
+    /// Log posterior probability of each class in `classes`, normalised so their
+    /// probabilities sum to one.
+    pub fn log_posteriors(&self, values: &[f64]) -> Vec<f64> {
+        let joint = self.joint(values);
+        let total = log_sum_exp(&joint);
+        joint.iter().map(|j| j - total).collect()
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        self.classes[linalg::argmax(&self.joint(values))]
+    }
+
+    /// Log odds of `class` against all the others; positive when it is the more likely.
+    pub fn log_odds(&self, values: &[f64], class: i8) -> f64 {
+        let joint = self.joint(values);
+        match self.classes.iter().position(|c| *c == class) {
+            Some(c) => {
+                let others: Vec<f64> = joint.iter().enumerate().filter(|(i, _)| *i != c).map(|(_, j)| *j).collect();
+                joint[c] - log_sum_exp(&others)
+            },
+            None => f64::NEG_INFINITY
+        }
+    }
+}
+
+
+/// `ln(sum(exp(x)))` without overflowing; the log of an empty sum is -inf.
+fn log_sum_exp(xs: &[f64]) -> f64 {
+    let max = xs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + xs.iter().map(|x| (x - max).exp()).sum::<f64>().ln()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bias plus two words; class 0 favours the first word 6:2 after Laplace smoothing,
+    /// class 1 the second.
+    fn samples() -> Vec<Sample> {
+        [([1.0, 3.0, 1.0], 0), ([1.0, 2.0, 0.0], 0), ([1.0, 0.0, 2.0], 1), ([1.0, 1.0, 3.0], 1)].iter()
+            .map(|(values, class)| Sample { values: values.to_vec(), class: *class })
+            .collect()
+    }
+
+    #[test]
+    fn smoothed_counts_give_the_likelihoods_and_the_bias_is_ignored() {
+        let model = NaiveBayes::fit(&samples(), 1.0).unwrap();
+        assert_eq!(model.used, vec![false, true, true]);
+        assert!((model.log_likelihoods[0][1] - 0.75f64.ln()).abs() < 1e-12);
+        assert!((model.log_likelihoods[1][1] - 0.25f64.ln()).abs() < 1e-12);
+
+        let posteriors = model.log_posteriors(&[1.0, 1.0, 0.0]);
+        assert!((posteriors[0].exp() - 0.75).abs() < 1e-12);
+        assert_eq!(model.predict(&[1.0, 2.0, 0.0]), 0);
+        assert_eq!(model.predict(&[1.0, 0.0, 2.0]), 1);
+        assert!(model.log_odds(&[5.0, 1.0, 1.0], 1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn refuses_anything_but_unscaled_non_negative_counts() {
+        assert!(NaiveBayes::fit(&samples(), 0.0).is_err());
+        let negative = vec![Sample { values: vec![1.0, -1.0], class: 0 }];
+        assert!(NaiveBayes::fit(&negative, 1.0).is_err());
+
+        let counts = FeatureSet::new(&["word_counts"]).unwrap();
+        assert!(check_features(&counts, Scaling::None).is_ok());
+        assert!(check_features(&counts, Scaling::ZScore).is_err());
+        assert!(check_features(&FeatureSet::new(&["words"]).unwrap(), Scaling::None).is_err());
+    }
+}
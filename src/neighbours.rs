@@ -0,0 +1,293 @@
+use std::fmt;
+use crate::linalg;
+use crate::perceptron::Sample;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Euclidean,
+    /// One minus the cosine similarity.
+    Cosine,
+    Manhattan,
+    /// Burrows's Delta: the mean absolute difference of the features' z-scores, using
+    /// the training samples' means and standard deviations.
+    Delta
+}
+
+impl Metric {
+    pub fn parse(name: &str) -> Result<Metric, String> {
+        match name {
+            "euclidean" => Ok(Metric::Euclidean),
+            "cosine" => Ok(Metric::Cosine),
+            "manhattan" => Ok(Metric::Manhattan),
+            "delta" => Ok(Metric::Delta),
+            other => Err(format!("Unknown metric `{}` (expected euclidean, cosine, manhattan or delta)", other))
+        }
+    }
+}
+
+/// The name accepted by `Metric::parse`.
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Metric::Euclidean => "euclidean",
+            Metric::Cosine => "cosine",
+            Metric::Manhattan => "manhattan",
+            Metric::Delta => "delta"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+/// k-nearest-neighbours: the majority class among the `k` training samples closest to
+/// a sample, with ties going to the class whose member is nearest.
+#[derive(Debug, Clone)]
+pub struct Knn {
+    pub metric: Metric,
+    pub k: usize,
+    pub samples: Vec<Sample>,
+    /// Per-feature means and standard deviations over `samples`, for Delta.
+    pub mean: Vec<f64>,
+    pub sd: Vec<f64>
+}
+
+impl Knn {
+    pub fn fit(samples: &[Sample], k: usize, metric: Metric) -> Knn {
+        let n = samples.len() as f64;
+        let dims = samples[0].values.len();
+        let mean: Vec<f64> = (0..dims).map(|j| samples.iter().map(|s| s.values[j]).sum::<f64>() / n).collect();
+        let sd: Vec<f64> = (0..dims)
+            .map(|j| (samples.iter().map(|s| (s.values[j] - mean[j]).powi(2)).sum::<f64>() / n).sqrt())
+            .collect();
+        Knn { metric, k: k.clamp(1, samples.len()), samples: samples.to_vec(), mean, sd }
+    }
+
+    pub fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        match self.metric {
+            Metric::Euclidean => a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt(),
+            Metric::Cosine => {
+                let (mut ab, mut aa, mut bb) = (0.0, 0.0, 0.0);
+                for (x, y) in a.iter().zip(b) {
+                    ab += x * y;
+                    aa += x * x;
+                    bb += y * y;
+                }
+                if aa > 0.0 && bb > 0.0 { 1.0 - ab / (aa * bb).sqrt() } else { 1.0 }
+            },
+            Metric::Manhattan => a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum(),
+            Metric::Delta => {
+                // constant features have no z-score and are left out
+                let varying: Vec<usize> = (0..a.len()).filter(|j| self.sd[*j] > 0.0).collect();
+                let total: f64 = varying.iter().map(|j| (a[*j] - b[*j]).abs() / self.sd[*j]).sum();
+                total / varying.len().max(1) as f64
+            }
+        }
+    }
+
+    /// Indices into `samples` of the `k` nearest, nearest first, with their distances.
+    pub fn neighbours(&self, values: &[f64]) -> Vec<(usize, f64)> {
+        let mut all: Vec<(usize, f64)> = self.samples.iter().enumerate().map(|(i, s)| (i, self.distance(values, &s.values))).collect();
+        all.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        all.truncate(self.k);
+        all
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        let classes: Vec<i8> = self.neighbours(values).iter().map(|(i, _)| self.samples[*i].class).collect();
+        let votes = |class: i8| classes.iter().filter(|c| **c == class).count();
+        // nearest first, and only a strictly larger vote takes over
+        let mut best = classes[0];
+        for c in &classes {
+            if votes(*c) > votes(best) {
+                best = *c;
+            }
+        }
+        best
+    }
+
+    /// Votes for class 1 less votes against, as a fraction of `k`: positive means class 1.
+    pub fn score(&self, values: &[f64]) -> f64 {
+        let neighbours = self.neighbours(values);
+        let ones = neighbours.iter().filter(|(i, _)| self.samples[*i].class == 1).count() as f64;
+        let margin = (2.0 * ones - neighbours.len() as f64) / neighbours.len() as f64;
+        // a tie goes to the nearest neighbour's class, so nudge it that way
+        if margin == 0.0 {
+            if self.samples[neighbours[0].0].class == 1 { 1e-6 } else { -1e-6 }
+        } else {
+            margin
+        }
+    }
+}
+
+
+/// Tibshirani et al.'s nearest shrunken centroids. Each class centroid is pulled
+/// towards the overall centroid by `shrinkage` standardised units, feature by
+/// feature, so features that barely separate the classes drop out entirely; a sample
+/// goes to the class with the nearest shrunken centroid, in standardised distance.
+#[derive(Debug, Clone)]
+pub struct ShrunkenCentroids {
+    pub shrinkage: f64,
+    pub classes: Vec<i8>,
+    pub log_priors: Vec<f64>,
+    pub centroids: Vec<Vec<f64>>,
+    /// Per-feature pooled within-class standard deviation plus its median, which keeps
+    /// features with tiny spread from dominating.
+    pub scale: Vec<f64>,
+    /// Features where at least one class centroid survives the shrinkage.
+    pub used: Vec<bool>
+}
+
+impl ShrunkenCentroids {
+    pub fn fit(samples: &[Sample], shrinkage: f64) -> Result<ShrunkenCentroids, String> {
+        let n = samples.len() as f64;
+        let dims = samples[0].values.len();
+        let mut classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+        classes.sort_unstable();
+        classes.dedup();
+        check_classes("Nearest shrunken centroids", &classes)?;
+
+        let mean = |members: &[&Sample]| -> Vec<f64> {
+            (0..dims).map(|j| members.iter().map(|s| s.values[j]).sum::<f64>() / members.len() as f64).collect()
+        };
+        let all: Vec<&Sample> = samples.iter().collect();
+        let overall = mean(&all);
+        let members: Vec<Vec<&Sample>> = classes.iter().map(|c| samples.iter().filter(|s| s.class == *c).collect()).collect();
+        let class_means: Vec<Vec<f64>> = members.iter().map(|m| mean(m)).collect();
+
+        let degrees = (n - classes.len() as f64).max(1.0);
+        let sd: Vec<f64> = (0..dims).map(|j| {
+            let within: f64 = members.iter().zip(&class_means)
+                .map(|(m, centroid)| m.iter().map(|s| (s.values[j] - centroid[j]).powi(2)).sum::<f64>())
+                .sum();
+            (within / degrees).sqrt()
+        }).collect();
+        let mut sorted = sd.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = sorted[sorted.len() / 2];
+        let scale: Vec<f64> = sd.iter().map(|s| s + median).collect();
+
+        let mut used = vec![false; dims];
+        let mut centroids = Vec::new();
+        for (m, centroid) in members.iter().zip(&class_means) {
+            let spread = (1.0 / m.len() as f64 - 1.0 / n).max(0.0).sqrt();
+            centroids.push((0..dims).map(|j| {
+                if scale[j] == 0.0 || spread == 0.0 {
+                    return overall[j];
+                }
+                let d = (centroid[j] - overall[j]) / (spread * scale[j]);
+                let shrunk = d.signum() * (d.abs() - shrinkage).max(0.0);
+                if shrunk != 0.0 {
+                    used[j] = true;
+                }
+                overall[j] + spread * scale[j] * shrunk
+            }).collect());
+        }
+
+        let log_priors = members.iter().map(|m| (m.len() as f64 / n).ln()).collect();
+        Ok(ShrunkenCentroids { shrinkage, classes, log_priors, centroids, scale, used })
+    }
+
+    /// Standardised squared distance to each class's shrunken centroid, less twice the
+    /// log prior; the smallest wins.
+    pub fn discriminants(&self, values: &[f64]) -> Vec<f64> {
+        self.centroids.iter().zip(&self.log_priors)
+            .map(|(centroid, prior)| {
+                let distance: f64 = (0..values.len())
+                    .filter(|j| self.used[*j])
+                    .map(|j| ((values[j] - centroid[j]) / self.scale[j]).powi(2))
+                    .sum();
+                distance - 2.0 * prior
+            })
+            .collect()
+    }
+
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        self.classes[linalg::argmin(&self.discriminants(values))]
+    }
+
+    /// How much nearer class 1's centroid is than the nearest other's: positive means class 1.
+    pub fn score(&self, values: &[f64]) -> f64 {
+        centroid_margin(&self.classes, &self.discriminants(values))
+    }
+}
+
+
+/// Nearest-centroid rules score class 1 against the rest, so they are only fitted to
+/// samples of class 1 and of at least one other class.
+pub fn check_classes(model: &str, classes: &[i8]) -> Result<(), String> {
+    if !classes.contains(&1) || classes.len() < 2 {
+        return Err(format!("{} needs samples of class 1 and of at least one other class", model));
+    }
+    Ok(())
+}
+
+/// For nearest-centroid rules, where the smallest discriminant wins: how much smaller
+/// class 1's is than the smallest of the others', so positive means class 1. The
+/// classes must pass `check_classes`.
+pub fn centroid_margin(classes: &[i8], discriminants: &[f64]) -> f64 {
+    let (mut one, mut others) = (f64::INFINITY, f64::INFINITY);
+    for (c, d) in classes.iter().zip(discriminants) {
+        if *c == 1 {
+            one = *d;
+        } else {
+            others = others.min(*d);
+        }
+    }
+    others - one
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(points: &[(f64, f64, i8)]) -> Vec<Sample> {
+        points.iter().map(|(x, y, class)| Sample { values: vec![*x, *y], class: *class }).collect()
+    }
+
+    #[test]
+    fn knn_takes_the_majority_and_breaks_ties_by_the_nearest() {
+        let training = samples(&[(0.0, 0.0, 0), (1.0, 0.0, 0), (5.0, 0.0, 1), (6.0, 0.0, 1), (7.0, 0.0, 1)]);
+        let knn = Knn::fit(&training, 3, Metric::Euclidean);
+        assert_eq!(knn.predict(&[0.5, 0.0]), 0);
+        assert_eq!(knn.predict(&[4.0, 0.0]), 1);
+        assert_eq!(knn.score(&[6.0, 0.0]), 1.0);
+
+        let pairs = Knn::fit(&training, 2, Metric::Euclidean);
+        assert_eq!(pairs.predict(&[2.8, 0.0]), 0);
+        assert!(pairs.score(&[2.8, 0.0]) < 0.0);
+        assert_eq!(Knn::fit(&training, 50, Metric::Euclidean).k, 5);
+    }
+
+    #[test]
+    fn distances_follow_each_metric() {
+        let training = samples(&[(0.0, 0.0, 0), (2.0, 10.0, 1)]);
+        let distance = |metric| Knn::fit(&training, 1, metric).distance(&[1.0, 0.0], &[4.0, 4.0]);
+        assert!((distance(Metric::Euclidean) - 5.0).abs() < 1e-12);
+        assert!((distance(Metric::Manhattan) - 7.0).abs() < 1e-12);
+        assert!((distance(Metric::Cosine) - (1.0 - 4.0 / 32f64.sqrt())).abs() < 1e-12);
+        // standard deviations 1 and 5, so z-score differences 3 and 0.8
+        assert!((distance(Metric::Delta) - 1.9).abs() < 1e-12);
+    }
+
+    #[test]
+    fn shrinkage_drops_features_that_barely_separate_the_classes() {
+        // x separates the classes, y is noise
+        let training = samples(&[(0.0, 1.0, 0), (0.2, -1.0, 0), (0.1, 0.5, 0), (3.0, 0.9, 1), (3.2, -0.8, 1), (3.1, -0.4, 1)]);
+        let model = ShrunkenCentroids::fit(&training, 1.0).unwrap();
+        assert_eq!(model.used, vec![true, false]);
+        assert_eq!(model.predict(&[2.5, 5.0]), 1);
+        assert!(model.score(&[2.5, 5.0]) > 0.0 && model.score(&[0.5, -5.0]) < 0.0);
+        assert_eq!(ShrunkenCentroids::fit(&training, 100.0).unwrap().used, vec![false, false]);
+    }
+
+    #[test]
+    fn centroids_need_class_one_and_another_class() {
+        let ones = samples(&[(0.0, 0.0, 1), (1.0, 1.0, 1)]);
+        assert!(ShrunkenCentroids::fit(&ones, 0.0).is_err());
+        let without_one = samples(&[(0.0, 0.0, 0), (1.0, 1.0, 2)]);
+        assert!(ShrunkenCentroids::fit(&without_one, 0.0).is_err());
+        assert_eq!(centroid_margin(&[0, 1, 2], &[3.0, 1.0, 2.5]), 1.5);
+    }
+}
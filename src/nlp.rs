@@ -1,78 +1,76 @@
 pub fn is_pronoun(w: &str) -> bool {
-    match w {
-        "i" => true,
-        "you" => true,
-        "he" => true,
-        "she" => true,
-        "we" => true,
-        "they" => true,
-        "it" => true,
-        "whoever" => true,
-        _ => false
-    }
+    matches!(w,
+        "i" |
+        "you" |
+        "he" |
+        "she" |
+        "we" |
+        "they" |
+        "it" |
+        "whoever"
+    )
 }
 
 pub fn is_conjunction(w: &str) -> bool {
-    match w {
-        "and" => true,
-        "but" => true,
-        "or" => true,
-        "nor" => true,
-        "for" => true,
-        "yet" => true,
-        "so" => true,
-        "after" => true,
-        "although" => true,
-        "as" => true,
-        "as if" => true,
-        "as long as" => true,
-        "as much as" => true,
-        "as soon as" => true,
-        "as though" => true,
-        "because" => true,
-        "before" => true,
-        "even " => true,
-        "even if" => true,
-        "even though" => true,
-        "if" => true,
-        "if only" => true,
-        "if when" => true,
-        "if then" => true,
-        "inasmuch" => true,
-        "in order that" => true,
-        "just as" => true,
-        "lest" => true,
-        "now" => true,
-        "now since" => true,
-        "now that" => true,
-        "now when" => true,
-        "once" => true,
-        "provided" => true,
-        "provided that" => true,
-        "rather than" => true,
-        "since" => true,
-        "so that" => true,
-        "supposing" => true,
-        "than" => true,
-        "that" => true,
-        "though" => true,
-        "til" => true,
-        "unless" => true,
-        "until" => true,
-        "when" => true,
-        "whenever" => true,
-        "where" => true,
-        "whereas" => true,
-        "where if" => true,
-        "wherever" => true,
-        "whether" => true,
-        "which" => true,
-        "while" => true,
-        "who" => true,
-        "whoever" => true,
-        "why" => true,
-        _ => false
-    }
+    matches!(w,
+        "and" |
+        "but" |
+        "or" |
+        "nor" |
+        "for" |
+        "yet" |
+        "so" |
+        "after" |
+        "although" |
+        "as" |
+        "as if" |
+        "as long as" |
+        "as much as" |
+        "as soon as" |
+        "as though" |
+        "because" |
+        "before" |
+        "even " |
+        "even if" |
+        "even though" |
+        "if" |
+        "if only" |
+        "if when" |
+        "if then" |
+        "inasmuch" |
+        "in order that" |
+        "just as" |
+        "lest" |
+        "now" |
+        "now since" |
+        "now that" |
+        "now when" |
+        "once" |
+        "provided" |
+        "provided that" |
+        "rather than" |
+        "since" |
+        "so that" |
+        "supposing" |
+        "than" |
+        "that" |
+        "though" |
+        "til" |
+        "unless" |
+        "until" |
+        "when" |
+        "whenever" |
+        "where" |
+        "whereas" |
+        "where if" |
+        "wherever" |
+        "whether" |
+        "which" |
+        "while" |
+        "who" |
+        "whoever" |
+        "why"
+    )
 }
 
 pub fn replace_word(w: String) -> String {
@@ -120,5 +118,116 @@ pub fn replace_word(w: String) -> String {
             "z." => "",
             other => other
         };
-    return resp.to_owned()
-}
\ No newline at end of file
+    resp.to_owned()
+}
+
+/// True if a raw token opens a Gutenberg `_italic_` span, allowing for leading
+/// brackets or quotation marks.
+pub fn opens_italics(w: &str) -> bool {
+    w.trim_start_matches(&['(', '[', '\"', '“', '‘', '\''][..]).starts_with('_')
+}
+
+/// True if a raw token starts with a capital letter, ignoring leading punctuation.
+/// The pronoun "I" is always capitalised and so carries no information.
+pub fn is_capitalised(w: &str) -> bool {
+    let letters = w.trim_start_matches(|c: char| !c.is_alphabetic());
+    match letters.chars().next() {
+        Some(c) => c.is_uppercase() && letters.trim_end_matches(|c: char| !c.is_alphabetic()) != "I",
+        None => false
+    }
+}
+
+/// Rough English syllable count: vowel groups, less a silent final "e" or "-ed".
+/// It is wrong for plenty of words, but consistently so across authors, which is
+/// all a stylometric feature needs.
+pub fn syllables(w: &str) -> usize {
+    let letters: Vec<char> = w.chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    if letters.is_empty() {
+        return 0;
+    }
+    if letters.len() <= 3 {
+        return 1;
+    }
+
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in &letters {
+        let vowel = is_vowel(*c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    let n = letters.len();
+    let (last, second_last, third_last) = (letters[n-1], letters[n-2], letters[n-3]);
+    let consonant_le = second_last == 'l' && !is_vowel(third_last);
+    if last == 'e' && !consonant_le && !is_vowel(second_last) {
+        // "made", "whence", but not "table"
+        count -= 1;
+    } else if last == 'd' && second_last == 'e' && !matches!(third_last, 't' | 'd') && !is_vowel(third_last) {
+        // "loved", "seemed", but not "wanted"
+        count -= 1;
+    }
+
+    count.max(1)
+}
+
+/// Overlapping character n-grams of a cleaned word, with `_` marking the word
+/// boundaries so that prefixes and suffixes get grams of their own ("_th", "ed_").
+pub fn char_ngrams(w: &str, n: usize) -> Vec<String> {
+    let padded: Vec<char> = std::iter::once('_').chain(w.chars()).chain(std::iter::once('_')).collect();
+    padded.windows(n).map(|gram| gram.iter().collect()).collect()
+}
+
+/// A raw token as it is counted in the word frequencies, and whether it ends a
+/// sentence: lowercased, stripped of quotes, brackets and commas, with abbreviations
+/// spelt out, and only then stripped of sentence punctuation, so that "Mr." is
+/// "mister" rather than the end of a sentence.
+pub fn normalise(w: &str) -> (String, bool) {
+    let keyword = w.to_lowercase().replace(&['(', ')', '§', '*', '\"', '“', '”', ';', ':', '\'', '_', ','][..], "");
+    let keyword = replace_word(keyword);
+    let ends_sentence = keyword.contains(&['.', '?', '!'][..]);
+    (keyword.replace(&['.', '?', '!'][..], ""), ends_sentence)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn italics_open_after_leading_quotes_and_brackets() {
+        assert!(opens_italics("_Robinson"));
+        assert!(opens_italics("(_vide"));
+        assert!(opens_italics("“_Pyrates_”"));
+        assert!(!opens_italics("Crusoe_"));
+        assert!(!opens_italics("word"));
+    }
+
+    #[test]
+    fn capitalisation_ignores_punctuation_and_the_pronoun_i() {
+        assert!(is_capitalised("London,"));
+        assert!(is_capitalised("“Friday"));
+        assert!(!is_capitalised("island"));
+        assert!(!is_capitalised("I"));
+        assert!(!is_capitalised("I,"));
+        assert!(!is_capitalised("--"));
+    }
+
+    #[test]
+    fn syllables_follow_the_silent_e_and_ed_rules() {
+        assert_eq!(syllables("the"), 1);
+        assert_eq!(syllables("island"), 2);
+        assert_eq!(syllables("made"), 1);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("loved"), 1);
+        assert_eq!(syllables("wanted"), 2);
+        assert_eq!(syllables("Adventure,"), 3);
+        assert_eq!(syllables("1719"), 0);
+    }
+}
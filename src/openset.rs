@@ -0,0 +1,116 @@
+use crate::linalg;
+use crate::perceptron::Sample;
+
+
+/// Open-set attribution by distance to author profiles. Each candidate author's
+/// profile is the centroid of their training samples; a new sample goes to the
+/// nearest profile, or to none of them if even the nearest is further away than
+/// `threshold`.
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    pub authors: Vec<String>,
+    pub centroids: Vec<Vec<f64>>,
+    pub threshold: f64
+}
+
+/// The nearest profile and the distance to it; `author` is `None` when the sample is
+/// rejected as not written by any candidate.
+#[derive(Debug, Clone)]
+pub struct Attribution {
+    pub author: Option<usize>,
+    pub nearest: usize,
+    pub distance: f64
+}
+
+impl Profiles {
+    /// `authors[i]` wrote `samples[i]`. The threshold is calibrated on held-out training
+    /// documents: each document is measured against its own author's profile rebuilt
+    /// without it, and the threshold is set so that a fraction `coverage` of them would
+    /// have been accepted. Authors with a single document can't be held out and don't
+    /// contribute to the threshold.
+    pub fn fit(samples: &[Sample], authors: &[String], coverage: f64) -> Profiles {
+        let mut names: Vec<String> = authors.to_vec();
+        names.sort();
+        names.dedup();
+
+        let members: Vec<Vec<usize>> = names.iter()
+            .map(|name| (0..samples.len()).filter(|i| authors[*i] == *name).collect())
+            .collect();
+        let centroids: Vec<Vec<f64>> = members.iter().map(|m| centroid(samples, m)).collect();
+
+        let mut held_out = Vec::new();
+        for m in members.iter().filter(|m| m.len() > 1) {
+            for i in m {
+                let others: Vec<usize> = m.iter().cloned().filter(|j| j != i).collect();
+                held_out.push(distance(&samples[*i].values, &centroid(samples, &others)));
+            }
+        }
+        held_out.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let threshold = if held_out.is_empty() {
+            f64::INFINITY
+        } else {
+            let rank = (coverage * held_out.len() as f64).ceil() as usize;
+            held_out[rank.clamp(1, held_out.len()) - 1]
+        };
+
+        Profiles { authors: names, centroids, threshold }
+    }
+
+    pub fn attribute(&self, values: &[f64]) -> Attribution {
+        let distances: Vec<f64> = self.centroids.iter().map(|c| distance(values, c)).collect();
+        let nearest = linalg::argmin(&distances);
+        let author = if distances[nearest] <= self.threshold { Some(nearest) } else { None };
+        Attribution { author, nearest, distance: distances[nearest] }
+    }
+}
+
+
+fn centroid(samples: &[Sample], members: &[usize]) -> Vec<f64> {
+    let dims = samples[members[0]].values.len();
+    let mut c = vec![0.0; dims];
+    for i in members {
+        for (cj, x) in c.iter_mut().zip(&samples[*i].values) {
+            *cj += x / members.len() as f64;
+        }
+    }
+    c
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    assert_eq!(a.len(), b.len());
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fit(coverage: f64) -> Profiles {
+        let samples: Vec<Sample> = [0.0, 2.0, 10.0, 12.0, 14.0].iter().map(|x| Sample { values: vec![*x], class: 0 }).collect();
+        let authors: Vec<String> = ["b", "b", "a", "a", "a"].iter().map(|a| a.to_string()).collect();
+        Profiles::fit(&samples, &authors, coverage)
+    }
+
+    #[test]
+    fn threshold_comes_from_held_out_distances_to_each_authors_profile() {
+        // held out: a at 3, 0 and 3 from the other two; b at 2 from the other one
+        let profiles = fit(0.6);
+        assert_eq!(profiles.authors, vec!["a", "b"]);
+        assert_eq!(profiles.centroids, vec![vec![12.0], vec![1.0]]);
+        assert_eq!(profiles.threshold, 2.0);
+        assert_eq!(fit(1.0).threshold, 3.0);
+    }
+
+    #[test]
+    fn samples_far_from_every_profile_are_rejected() {
+        let profiles = fit(0.6);
+        assert_eq!(profiles.attribute(&[13.0]).author, Some(0));
+        assert_eq!(profiles.attribute(&[1.5]).author, Some(1));
+        let rejected = profiles.attribute(&[6.0]);
+        assert_eq!((rejected.author, rejected.nearest, rejected.distance), (None, 1, 5.0));
+
+        let single = Profiles::fit(&[Sample { values: vec![0.0], class: 0 }], &["a".to_string()], 0.9);
+        assert_eq!(single.threshold, f64::INFINITY);
+    }
+}
@@ -0,0 +1,199 @@
+use rand::Rng;
+use crate::calibration::Calibration;
+use crate::features::{self, FeatureSet};
+use crate::kernel::Kernel;
+use crate::logistic::Penalty;
+use crate::mlp::{Activation, MlpConfig};
+use crate::neighbours::Metric;
+use crate::perceptron::{PerceptronConfig, Variant};
+use crate::scaling::Scaling;
+use crate::selection::Selection;
+
+
+/// Everything that can be set from the command line.
+pub struct Options {
+    pub features: FeatureSet,
+    pub selection: Selection,
+    pub scaling: Scaling,
+    pub model: String,
+    /// Used by the SVM and the kernel perceptron.
+    pub kernel: Kernel,
+    pub perceptron: PerceptronConfig,
+    /// Fitted on out-of-fold decision values from `folds`-fold cross-validation.
+    pub calibration: Calibration,
+    pub folds: usize,
+    /// Fraction of held-out training books the open-set distance threshold accepts;
+    /// `None` leaves open-set attribution off.
+    pub open_set: Option<f64>,
+    /// Upper bound on the fraction of training books the one-class SVM leaves outside.
+    pub nu: f64,
+    /// Rounds of random features and impostors for the General Impostors method.
+    pub iterations: usize,
+    /// Context length of the PPM compression models.
+    pub ppm_order: usize,
+    /// Lidstone smoothing for naive Bayes; 1 is Laplace smoothing.
+    pub alpha: f64,
+    /// Regularisation of logistic regression.
+    pub penalty: Penalty,
+    /// k for k-nearest-neighbours.
+    pub neighbours: usize,
+    pub metric: Metric,
+    /// Standardised units each nearest shrunken centroid is pulled towards the overall one.
+    pub shrinkage: f64,
+    /// Trees in the random forest.
+    pub trees: usize,
+    /// Deepest a decision tree may grow.
+    pub depth: usize,
+    pub mlp: MlpConfig,
+    /// Added to the diagonal of the discriminant analysis's within-class covariance,
+    /// relative to its mean variance.
+    pub ridge: f64,
+    /// Path prefix for files written by `unmask` and `pca`; each has its own default.
+    pub output: Option<String>,
+    pub seed: u64
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Options {
+        let defaults = PerceptronConfig::default();
+        let kernel = parse_option(args, "--kernel", Kernel::parse).unwrap_or(Kernel::Linear);
+        let perceptron = PerceptronConfig {
+            rate: parse_option(args, "--rate", parse_number).unwrap_or(defaults.rate),
+            max_epochs: parse_option(args, "--epochs", parse_number).unwrap_or(defaults.max_epochs),
+            init: parse_option(args, "--init", parse_range).unwrap_or(defaults.init),
+            shuffle: parse_option(args, "--shuffle", parse_yes_no).unwrap_or(defaults.shuffle),
+            tolerance: parse_option(args, "--tolerance", parse_number).unwrap_or(defaults.tolerance),
+            variant: parse_option(args, "--variant", Variant::parse).unwrap_or(defaults.variant),
+            kernel
+        };
+        let network = MlpConfig::default();
+        let mlp = MlpConfig {
+            hidden: parse_option(args, "--hidden", parse_sizes).unwrap_or(network.hidden),
+            activation: parse_option(args, "--activation", Activation::parse).unwrap_or(network.activation),
+            rate: parse_option(args, "--mlp-rate", parse_number).unwrap_or(network.rate),
+            epochs: parse_option(args, "--mlp-epochs", parse_number).unwrap_or(network.epochs),
+            batch: parse_option(args, "--batch", parse_number).unwrap_or(network.batch),
+            momentum: parse_option(args, "--momentum", parse_number).unwrap_or(network.momentum),
+            decay: parse_option(args, "--decay", parse_number).unwrap_or(network.decay)
+        };
+
+        Options {
+            features: parse_option(args, "--features", FeatureSet::parse).unwrap_or_else(|| FeatureSet::new(&features::DEFAULT).unwrap()),
+            selection: parse_option(args, "--select", Selection::parse).unwrap_or(Selection::All),
+            scaling: parse_option(args, "--scaling", Scaling::parse).unwrap_or(Scaling::ZScore),
+            model: parse_option(args, "--model", |m| Ok(m.to_string())).unwrap_or_else(|| "perceptron".to_string()),
+            kernel,
+            perceptron,
+            calibration: parse_option(args, "--calibrate", Calibration::parse).unwrap_or(Calibration::None),
+            folds: parse_option(args, "--folds", parse_folds).unwrap_or(5),
+            open_set: parse_option(args, "--open-set", parse_fraction),
+            nu: parse_option(args, "--nu", parse_fraction).unwrap_or(0.1),
+            iterations: parse_option(args, "--iterations", parse_number).unwrap_or(100),
+            ppm_order: parse_option(args, "--ppm-order", parse_number).unwrap_or(5),
+            alpha: parse_option(args, "--alpha", parse_number).unwrap_or(1.0),
+            penalty: parse_option(args, "--penalty", Penalty::parse).unwrap_or(Penalty::L2 { lambda: 0.01 }),
+            neighbours: parse_option(args, "--neighbours", parse_number).unwrap_or(3),
+            metric: parse_option(args, "--metric", Metric::parse).unwrap_or(Metric::Euclidean),
+            shrinkage: parse_option(args, "--shrinkage", parse_number).unwrap_or(1.0),
+            trees: parse_option(args, "--trees", parse_positive).unwrap_or(100),
+            depth: parse_option(args, "--depth", parse_number).unwrap_or(10),
+            mlp,
+            ridge: parse_option(args, "--ridge", parse_number).unwrap_or(0.001),
+            output: parse_option(args, "--output", |o| Ok(o.to_string())),
+            // Without --seed, pick one at random but still report it, so any run can be repeated.
+            seed: parse_option(args, "--seed", parse_number).unwrap_or_else(|| rand::thread_rng().gen())
+        }
+    }
+}
+
+
+/// The value following `flag` on the command line, parsed with `parse`. Bad or
+/// missing values end the program with a message rather than being ignored.
+fn parse_option<T>(args: &[String], flag: &str, parse: fn(&str) -> Result<T, String>) -> Option<T> {
+    let i = args.iter().position(|a| a == flag)?;
+    match args.get(i+1).map(|value| parse(value)) {
+        Some(Ok(value)) => Some(value),
+        Some(Err(e)) => {
+            println!("{}: {}", flag, e);
+            std::process::exit(1);
+        },
+        None => {
+            println!("{} needs a value", flag);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| format!("bad value `{}`, expected a number", value))
+}
+
+/// Cross-validation needs something to train on besides the held-out fold.
+fn parse_folds(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(k) if k >= 2 => Ok(k),
+        _ => Err(format!("bad value `{}`, expected at least 2 folds", value))
+    }
+}
+
+fn parse_positive(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("bad value `{}`, expected a whole number above 0", value))
+    }
+}
+
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(f) if f > 0.0 && f <= 1.0 => Ok(f),
+        _ => Err(format!("bad value `{}`, expected a fraction between 0 and 1", value))
+    }
+}
+
+/// `low:high`
+fn parse_range(value: &str) -> Result<(f64, f64), String> {
+    let bad = || format!("bad value `{}`, expected low:high", value);
+    let (low, high) = value.split_once(':').ok_or_else(bad)?;
+    match (low.parse::<f64>(), high.parse::<f64>()) {
+        (Ok(low), Ok(high)) if low <= high => Ok((low, high)),
+        _ => Err(bad())
+    }
+}
+
+/// Comma-separated layer widths, e.g. `16,8`; `none` for no hidden layer.
+fn parse_sizes(value: &str) -> Result<Vec<usize>, String> {
+    if value == "none" {
+        return Ok(Vec::new());
+    }
+    value.split(',')
+        .map(|w| match w.parse::<usize>() {
+            Ok(w) if w > 0 => Ok(w),
+            _ => Err(format!("bad value `{}`, expected comma-separated layer widths", value))
+        })
+        .collect()
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        other => Err(format!("bad value `{}`, expected yes or no", other))
+    }
+}
+
+
+/// Arguments that aren't options or option values. Every option takes a value.
+pub fn positional(args: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut skip = false;
+    for a in args.iter().skip(1) {
+        if skip {
+            skip = false;
+        } else if a.starts_with("--") {
+            skip = true;
+        } else {
+            out.push(a.clone());
+        }
+    }
+    out
+}
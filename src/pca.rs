@@ -0,0 +1,106 @@
+use crate::kernel::dot;
+use crate::linalg::{self, Matrix};
+
+
+/// Principal component analysis: the orthogonal directions of greatest variance in a
+/// set of rows, largest first.
+#[derive(Debug, Clone)]
+pub struct Pca {
+    pub mean: Vec<f64>,
+    /// Unit vectors.
+    pub components: Matrix,
+    /// The variance along each component.
+    pub variances: Vec<f64>,
+    /// The variance summed over every feature, so `variances[i] / total` is the share a
+    /// component explains.
+    pub total: f64
+}
+
+impl Pca {
+    /// The first `count` principal components. With more features than rows (the usual
+    /// case for word frequencies) this works from the rows' Gram matrix instead of the
+    /// features' covariance: the two share their non-zero eigenvalues, and the Gram
+    /// matrix is far smaller.
+    pub fn fit(rows: &[Vec<f64>], count: usize) -> Pca {
+        let n = rows.len();
+        let dims = rows[0].len();
+        let divisor = (n as f64 - 1.0).max(1.0);
+
+        let mean = column_means(rows);
+        let (variances, components) = if dims <= n {
+            let slices: Vec<&[f64]> = rows.iter().map(|r| r.as_slice()).collect();
+            linalg::symmetric_eigen(&linalg::covariance(&slices).1)
+        } else {
+            let centred: Matrix = rows.iter().map(|r| r.iter().zip(&mean).map(|(x, m)| x - m).collect()).collect();
+            let gram: Matrix = centred.iter().map(|a| centred.iter().map(|b| dot(a, b) / divisor).collect()).collect();
+            let (variances, vectors) = linalg::symmetric_eigen(&gram);
+            // each eigenvector u of the Gram matrix maps to the component X'u, normalised
+            let components = vectors.iter().map(|u| {
+                let component: Vec<f64> = (0..dims).map(|j| centred.iter().zip(u).map(|(row, w)| row[j] * w).sum()).collect();
+                let norm = dot(&component, &component).sqrt();
+                component.iter().map(|c| if norm > 0.0 { c / norm } else { 0.0 }).collect()
+            }).collect();
+            (variances, components)
+        };
+
+        let total = (0..dims).map(|j| rows.iter().map(|r| (r[j] - mean[j]).powi(2)).sum::<f64>() / divisor).sum();
+        let count = count.min(variances.len());
+        Pca {
+            mean,
+            components: components.into_iter().take(count).collect(),
+            variances: variances.into_iter().take(count).map(|v: f64| v.max(0.0)).collect(),
+            total
+        }
+    }
+
+    /// The coordinates of `values` along each component.
+    pub fn project(&self, values: &[f64]) -> Vec<f64> {
+        let centred: Vec<f64> = values.iter().zip(&self.mean).map(|(x, m)| x - m).collect();
+        self.components.iter().map(|c| dot(c, &centred)).collect()
+    }
+}
+
+
+fn column_means(rows: &[Vec<f64>]) -> Vec<f64> {
+    (0..rows[0].len()).map(|j| rows.iter().map(|r| r[j]).sum::<f64>() / rows.len() as f64).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variance(values: &[f64]) -> f64 {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+    }
+
+    #[test]
+    fn points_on_a_line_have_one_component() {
+        let rows: Vec<Vec<f64>> = [-2.0, -1.0, 0.5, 1.0, 1.5].iter().map(|t| vec![1.0 + t, 3.0 + 2.0 * t]).collect();
+        let model = Pca::fit(&rows, 2);
+        let first = &model.components[0];
+        assert!((first[1] / first[0] - 2.0).abs() < 1e-9);
+        assert!((model.variances[0] - model.total).abs() < 1e-9);
+        assert!(model.variances[1].abs() < 1e-9);
+        assert!(model.project(&model.mean).iter().all(|x| x.abs() < 1e-12));
+    }
+
+    #[test]
+    fn the_gram_route_matches_the_variance_of_the_projections() {
+        // more features than rows
+        let rows = vec![vec![1.0, 0.0, 2.0, 0.0, 1.0], vec![0.0, 1.0, 0.0, 3.0, 1.0], vec![2.0, 2.0, 1.0, 1.0, 0.0]];
+        let model = Pca::fit(&rows, 5);
+        // three rows span a plane about their mean
+        assert_eq!(model.components.len(), 3);
+        assert!(model.variances[2].abs() < 1e-9);
+        assert!((model.variances.iter().sum::<f64>() - model.total).abs() < 1e-9);
+        let projected: Vec<Vec<f64>> = rows.iter().map(|r| model.project(r)).collect();
+        for c in 0..2 {
+            let along: Vec<f64> = projected.iter().map(|p| p[c]).collect();
+            assert!((variance(&along) - model.variances[c]).abs() < 1e-9);
+            assert!((dot(&model.components[c], &model.components[c]) - 1.0).abs() < 1e-9);
+        }
+        assert!(dot(&model.components[0], &model.components[1]).abs() < 1e-9);
+    }
+}
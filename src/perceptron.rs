@@ -1,10 +1,14 @@
 use std::ops::{Mul, Add};
 use std::iter::Sum;
 use rand::Rng;
+use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
 use std::fmt;
+use crate::kernel::Kernel;
+use crate::linalg;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Sample {
     pub values: Vec<f64>,
     pub class: i8
@@ -13,40 +17,37 @@ pub struct Sample {
 impl fmt::Display for Sample {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{{")?;
-        let mut n = 0;
-        for v in &self.values {
-            if n == 0 {
-                ();
-            } else if n == 1 {
+        // values[0] is the bias term, which isn't worth printing
+        for (n, v) in self.values.iter().enumerate().skip(1) {
+            if n == 1 {
                 write!(f, "{}", v)?;
             } else {
                 write!(f, ", {}", v)?;
             }
-            n += 1;
         };
         write!(f, "}}, ")
     }
 }
 
 
-fn dot<T>(v1: &Vec<T>, v2: &Vec<T>) -> T
+fn dot<T>(v1: &[T], v2: &[T]) -> T
 where 
     T: Mul<Output = T> + Copy + Sum
  {
     assert_eq!(v1.len(), v2.len());
-    return v1.iter().zip(v2.iter()).map(|(x, y)| *x * *y).sum();
+    v1.iter().zip(v2.iter()).map(|(x, y)| *x * *y).sum()
 }
 
-fn sum<T>(v1: &Vec<T>, v2: &Vec<T>) -> Vec<T>
+fn sum<T>(v1: &[T], v2: &[T]) -> Vec<T>
 where 
     T: Add<Output = T> + Copy + Sum
  {
     assert_eq!(v1.len(), v2.len());
-    return v1.iter().zip(v2.iter()).map(|(x, y)| *x + *y).collect();
+    v1.iter().zip(v2.iter()).map(|(x, y)| *x + *y).collect()
 }
 
 
-fn update_weights(weights: &Vec<f64>, rate: f64, xj: &Sample, yj: i8) -> Vec<f64> {
+fn update_weights(weights: &[f64], rate: f64, xj: &Sample, yj: i8) -> Vec<f64> {
 
     // let mut output: Vec<f64> = Vec::new();
     let mut temp: Vec<f64> = Vec::new();
@@ -55,29 +56,129 @@ fn update_weights(weights: &Vec<f64>, rate: f64, xj: &Sample, yj: i8) -> Vec<f64
         temp.push( rate * ((xj.class - yj) as f64) * i )
     }
 
-    let output = sum(&temp, &weights); 
-
-    return output;
+    sum(&temp, weights)
 }
 
 
-fn f(w: &Vec<f64>, xj: &Sample) -> i8 {
-    if dot(&w, &xj.values) > 0.0 {
-        return 1;
+fn f(w: &[f64], xj: &Sample) -> i8 {
+    if dot(w, &xj.values) > 0.0 {
+        1
     } else {
-        return 0;
+        0
+    }
+}
+
+/// Which weights `train` hands back once the passes over the data are done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    /// The weights after the last update.
+    Plain,
+    /// The mean of the weights over every step of training, which damps the
+    /// oscillation on data that isn't linearly separable.
+    Averaged,
+    /// Freund & Schapire's voted perceptron: every intermediate weight vector votes,
+    /// weighted by the number of samples it survived.
+    Voted,
+    /// The dual form: keep every sample that caused a mistake and predict with a
+    /// kernel sum over them, so the boundary needn't be linear in the features.
+    Kernel
+}
+
+impl Variant {
+    pub fn parse(name: &str) -> Result<Variant, String> {
+        match name {
+            "plain" => Ok(Variant::Plain),
+            "averaged" => Ok(Variant::Averaged),
+            "voted" => Ok(Variant::Voted),
+            "kernel" => Ok(Variant::Kernel),
+            other => Err(format!("Unknown perceptron variant `{}` (expected plain, averaged, voted or kernel)", other))
+        }
     }
 }
 
-pub fn train(samples: &Vec<Sample>) -> Vec<f64> {
-    let mut rng = rand::thread_rng();
+
+/// Hyperparameters for `train`. The defaults are the values the perceptron was
+/// originally hard-coded with.
+#[derive(Debug, Clone)]
+pub struct PerceptronConfig {
+    pub rate: f64,
+    pub max_epochs: usize,
+    /// Initial weights are drawn uniformly from `init.0..init.1` (all `init.0` if the range is empty).
+    pub init: (f64, f64),
+    /// Visit the samples in a fresh random order every epoch.
+    pub shuffle: bool,
+    /// Stop once the fraction of misclassified training samples in an epoch is at or below this.
+    pub tolerance: f64,
+    pub variant: Variant,
+    /// Only used by `Variant::Kernel`.
+    pub kernel: Kernel
+}
+
+impl Default for PerceptronConfig {
+    fn default() -> PerceptronConfig {
+        PerceptronConfig {
+            rate: 0.01,
+            max_epochs: 100,
+            init: (0.0, 0.01),
+            shuffle: false,
+            tolerance: 0.0,
+            variant: Variant::Plain,
+            kernel: Kernel::Linear
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct TrainingResult {
+    /// The final or averaged weights, depending on the variant.
+    pub weights: Vec<f64>,
+    /// Each weight vector the voted perceptron passed through, with its vote. Empty
+    /// for the other variants.
+    pub votes: Vec<(Vec<f64>, usize)>,
+    /// The kernel perceptron, in which case `weights` is empty.
+    pub dual: Option<KernelPerceptron>,
+    /// Misclassified training samples in each epoch
+    pub epoch_errors: Vec<usize>,
+    pub converged: bool,
+    pub samples: usize
+}
+
+impl fmt::Display for TrainingResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let epochs = self.epoch_errors.len();
+        let errors = self.epoch_errors.last().cloned().unwrap_or(0);
+        if let Some(dual) = &self.dual {
+            let converged = if self.converged { "converged" } else { "did not converge" };
+            writeln!(f, "Kernel perceptron {} after {} epochs ({}/{} errors) with {} support samples ({} kernel)", converged, epochs, errors, self.samples, dual.support.len(), dual.kernel)?;
+        } else if self.converged {
+            writeln!(f, "Perceptron converged after {} epochs ({}/{} errors) with weights: {:?}", epochs, errors, self.samples, self.weights)?;
+        } else {
+            writeln!(f, "Perceptron did not converge! Data may not be linearly-separable, but you can try more --epochs or a different --rate.")?;
+            writeln!(f, "Perceptron weights after {} epochs: {:?} ({}/{} errors)", epochs, self.weights, errors, self.samples)?;
+        }
+        if !self.votes.is_empty() {
+            writeln!(f, "Voting with {} weight vectors", self.votes.len())?;
+        }
+        write!(f, "Errors per epoch: {:?}", self.epoch_errors)
+    }
+}
+
+
+pub fn train(samples: &[Sample], config: &PerceptronConfig, rng: &mut StdRng) -> TrainingResult {
+    if config.variant == Variant::Kernel {
+        return train_kernel(samples, config, rng);
+    }
+
     let mut weights = Vec::new();
-    let rate = 0.01;
-    let max_epochs = 100;
     
-    let sample = samples.get(0).unwrap();
+    let sample = samples.first().unwrap();
     for _i in &sample.values {
-        weights.push(rng.gen_range(0.0..0.01))
+        if config.init.1 > config.init.0 {
+            weights.push(rng.gen_range(config.init.0..config.init.1))
+        } else {
+            weights.push(config.init.0)
+        }
     }
 
     /*
@@ -96,38 +197,137 @@ pub fn train(samples: &Vec<Sample>) -> Vec<f64> {
     println!("\n");
     */
 
-    let mut errors = 0;
-    let mut epoch = 1;
-    while epoch < max_epochs {
-        errors = 0;
-        for s in samples {
-            let yj = f(&weights, &s);
-            weights = update_weights(&weights, rate, &s, yj);
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    let mut total = vec![0.0; weights.len()];
+    let mut steps = 0;
+    let mut votes = Vec::new();
+    let mut survived = 0;
+    let mut epoch_errors = Vec::new();
+    let mut converged = false;
+    for _epoch in 0..config.max_epochs {
+        if config.shuffle {
+            order.shuffle(rng);
+        }
+        let mut errors = 0;
+        for s in order.iter().map(|i| &samples[*i]) {
+            let yj = f(&weights, s);
             if (s.class - yj) != 0 {
                 errors += 1;
+                if config.variant == Variant::Voted && survived > 0 {
+                    votes.push((weights.clone(), survived));
+                }
+                weights = update_weights(&weights, config.rate, s, yj);
+                survived = 1;
+            } else {
+                survived += 1;
+            }
+            if config.variant == Variant::Averaged {
+                total = sum(&total, &weights);
+                steps += 1;
+            }
+        }
+        epoch_errors.push(errors);
+        if errors as f64 <= config.tolerance * samples.len() as f64 {
+            converged = true;
+            break;
+        }
+    }
+
+    match config.variant {
+        Variant::Plain | Variant::Kernel => (),
+        Variant::Averaged => weights = total.iter().map(|w| w / steps.max(1) as f64).collect(),
+        Variant::Voted => votes.push((weights.clone(), survived))
+    }
+
+    TrainingResult { weights, votes, dual: None, epoch_errors, converged, samples: samples.len() }
+}
+
+
+/// A kernel perceptron: the samples it got wrong during training, each with the sum
+/// of its signed updates.
+#[derive(Debug, Clone)]
+pub struct KernelPerceptron {
+    pub kernel: Kernel,
+    pub support: Vec<Vec<f64>>,
+    pub coef: Vec<f64>
+}
+
+impl KernelPerceptron {
+    /// Positive means class 1.
+    pub fn decision(&self, values: &[f64]) -> f64 {
+        self.support.iter().zip(&self.coef).map(|(s, c)| c * self.kernel.apply(s, values)).sum()
+    }
+
+    pub fn classify(&self, samples: &[Sample]) -> Vec<i8> {
+        samples.iter().map(|s| (self.decision(&s.values) > 0.0) as i8).collect()
+    }
+}
+
+
+/// The same mistake-driven loop as `train`, but each mistake adds `rate * (class - prediction)`
+/// to that sample's coefficient instead of updating a weight vector.
+fn train_kernel(samples: &[Sample], config: &PerceptronConfig, rng: &mut StdRng) -> TrainingResult {
+    let gram = config.kernel.gram(samples);
+    let mut coef = vec![0.0; samples.len()];
+
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    let mut epoch_errors = Vec::new();
+    let mut converged = false;
+    for _epoch in 0..config.max_epochs {
+        if config.shuffle {
+            order.shuffle(rng);
+        }
+        let mut errors = 0;
+        for j in order.iter().cloned() {
+            let decision: f64 = coef.iter().zip(&gram[j]).map(|(c, k)| c * k).sum();
+            let yj = (decision > 0.0) as i8;
+            if samples[j].class != yj {
+                errors += 1;
+                coef[j] += config.rate * (samples[j].class - yj) as f64;
             }
         }
-        if errors == 0 {
-            println!("Perceptron converged with weights: {:?} after {} epochs.", weights, epoch);
+        epoch_errors.push(errors);
+        if errors as f64 <= config.tolerance * samples.len() as f64 {
+            converged = true;
             break;
         }
-        epoch += 1;
     }
 
-    if epoch >= max_epochs {
-        println!("Perceptron did not converge! Data may not be linearly-separable, but you can try increasing `max_epochs`, training rate, etc.");
-        println!("Perceptron weights after {} epochs: {:?} ({}/{} errors)", epoch, weights, errors, samples.len());
+    let kept: Vec<usize> = (0..samples.len()).filter(|j| coef[*j] != 0.0).collect();
+    let dual = KernelPerceptron {
+        kernel: config.kernel,
+        support: kept.iter().map(|j| samples[*j].values.clone()).collect(),
+        coef: kept.iter().map(|j| coef[*j]).collect()
+    };
+
+    TrainingResult { weights: Vec::new(), votes: Vec::new(), dual: Some(dual), epoch_errors, converged, samples: samples.len() }
+}
+
+
+impl TrainingResult {
+    /// Decision values: the dot product, the kernel sum, or the voted perceptron's
+    /// vote. Positive means class 1.
+    pub fn scores(&self, samples: &[Sample]) -> Vec<f64> {
+        if let Some(dual) = &self.dual {
+            samples.iter().map(|s| dual.decision(&s.values)).collect()
+        } else if self.votes.is_empty() {
+            scores(&self.weights, samples)
+        } else {
+            samples.iter().map(|s| vote(&self.votes, s)).collect()
+        }
     }
 
-    return weights
+    pub fn classify(&self, samples: &[Sample]) -> Vec<i8> {
+        self.scores(samples).iter().map(|score| (*score > 0.0) as i8).collect()
+    }
 }
 
 
-pub fn test(weights: Vec<f64>, samples: &Vec<Sample>) {
+pub fn test(result: &TrainingResult, samples: &[Sample]) {
     let mut successes = 0;
     let mut total = 0;
-    for s in samples {
-        if f(&weights, &s) == s.class {
+    for (s, class) in samples.iter().zip(result.classify(samples)) {
+        if class == s.class {
             successes += 1;
         }
         total += 1;
@@ -139,10 +339,207 @@ pub fn test(weights: Vec<f64>, samples: &Vec<Sample>) {
 }
 
 
-pub fn classify(weights: Vec<f64>, samples: &Vec<Sample>) -> Vec<i8> {
+/// The dot product of each sample with the weights; positive means class 1.
+pub fn scores(weights: &[f64], samples: &[Sample]) -> Vec<f64> {
+    samples.iter().map(|s| dot(weights, &s.values)).collect()
+}
+
+
+pub fn classify(weights: Vec<f64>, samples: &[Sample]) -> Vec<i8> {
     let mut results = Vec::new();
     for s in samples {
-        results.push(f(&weights, &s));
+        results.push(f(&weights, s));
+    }
+    results
+}
+
+/// Sum of the votes for class 1 (+c) and class 0 (-c) over the voting weight vectors,
+/// as a fraction of all the votes cast, so between -1 and 1.
+pub fn vote(votes: &[(Vec<f64>, usize)], xj: &Sample) -> f64 {
+    let total: usize = votes.iter().map(|(_, c)| c).sum();
+    let sum: f64 = votes.iter()
+        .map(|(w, c)| if f(w, xj) == 1 { *c as f64 } else { -(*c as f64) })
+        .sum();
+    sum / total.max(1) as f64
+}
+
+
+pub fn classify_voted(votes: &[(Vec<f64>, usize)], samples: &[Sample]) -> Vec<i8> {
+    samples.iter().map(|s| (vote(votes, s) > 0.0) as i8).collect()
+}
+
+
+/// One weight vector per class; a sample goes to the class whose weights give the
+/// largest dot product. Classes are `0..weights.len()`.
+#[derive(Debug, Clone)]
+pub struct Multiclass {
+    pub weights: Vec<Vec<f64>>,
+    /// Misclassified training samples in each epoch
+    pub epoch_errors: Vec<usize>,
+    pub converged: bool
+}
+
+impl Multiclass {
+    pub fn predict(&self, values: &[f64]) -> i8 {
+        best_class(&self.weights, values) as i8
+    }
+
+    pub fn classify(&self, samples: &[Sample]) -> Vec<i8> {
+        samples.iter().map(|s| self.predict(&s.values)).collect()
+    }
+}
+
+impl fmt::Display for Multiclass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let converged = if self.converged { "converged" } else { "did not converge" };
+        writeln!(f, "Multi-class perceptron {} after {} epochs", converged, self.epoch_errors.len())?;
+        for (c, w) in self.weights.iter().enumerate() {
+            writeln!(f, "Class {} weights: {:?}", c, w)?;
+        }
+        write!(f, "Errors per epoch: {:?}", self.epoch_errors)
+    }
+}
+
+
+/// The class with the largest score; ties go to the lowest class.
+fn best_class(weights: &[Vec<f64>], values: &[f64]) -> usize {
+    let scores: Vec<f64> = weights.iter().map(|w| dot(w, values)).collect();
+    linalg::argmax(&scores)
+}
+
+
+/// Multi-class perceptron over classes `0..classes`. On a mistake the true class's
+/// weights move towards the sample and the predicted class's weights away from it.
+/// `Variant::Averaged` returns the averaged weights; every other variant trains plain.
+pub fn train_multiclass(samples: &[Sample], classes: usize, config: &PerceptronConfig, rng: &mut StdRng) -> Multiclass {
+    let dims = samples.first().unwrap().values.len();
+    let mut weights: Vec<Vec<f64>> = (0..classes)
+        .map(|_| (0..dims).map(|_| if config.init.1 > config.init.0 { rng.gen_range(config.init.0..config.init.1) } else { config.init.0 }).collect())
+        .collect();
+
+    let mut epoch_errors = Vec::new();
+    let mut converged = false;
+    let mut total = vec![vec![0.0; dims]; classes];
+    let mut steps = 0;
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    for _epoch in 0..config.max_epochs {
+        if config.shuffle {
+            order.shuffle(rng);
+        }
+        let mut errors = 0;
+        for s in order.iter().map(|i| &samples[*i]) {
+            let predicted = best_class(&weights, &s.values);
+            let actual = s.class as usize;
+            if predicted != actual {
+                errors += 1;
+                for (j, x) in s.values.iter().enumerate() {
+                    weights[actual][j] += config.rate * x;
+                    weights[predicted][j] -= config.rate * x;
+                }
+            }
+            if config.variant == Variant::Averaged {
+                for (t, w) in total.iter_mut().zip(&weights) {
+                    *t = sum(t, w);
+                }
+                steps += 1;
+            }
+        }
+        epoch_errors.push(errors);
+        if errors as f64 <= config.tolerance * samples.len() as f64 {
+            converged = true;
+            break;
+        }
+    }
+
+    if config.variant == Variant::Averaged {
+        weights = total.iter().map(|t| t.iter().map(|w| w / steps.max(1) as f64).collect()).collect();
+    }
+    Multiclass { weights, epoch_errors, converged }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+
+    /// Class 1 exactly when x > y, with the bias first.
+    fn separable() -> Vec<Sample> {
+        let points = [(2.0, 0.5), (1.5, -1.0), (0.5, -2.0), (3.0, 1.0), (-1.0, 0.5), (0.0, 2.0), (-2.0, -1.0), (1.0, 3.0)];
+        points.iter().map(|(x, y)| Sample { values: vec![1.0, *x, *y], class: (x > y) as i8 }).collect()
     }
-    return results;
-}
\ No newline at end of file
+
+    #[test]
+    fn converges_on_separable_data_and_records_each_epoch() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = separable();
+        let result = train(&samples, &PerceptronConfig::default(), &mut rng);
+        assert!(result.converged);
+        assert_eq!(result.epoch_errors.last(), Some(&0));
+        assert!(result.epoch_errors[..result.epoch_errors.len() - 1].iter().all(|e| *e > 0));
+        assert_eq!(result.classify(&samples), samples.iter().map(|s| s.class).collect::<Vec<i8>>());
+    }
+
+    #[test]
+    fn stops_at_the_epoch_limit_or_the_tolerance() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut samples = separable();
+        // the same point in both classes can never be fitted
+        samples.push(Sample { values: vec![1.0, 2.0, 0.5], class: 0 });
+        let config = PerceptronConfig { max_epochs: 7, init: (0.0, 0.0), ..PerceptronConfig::default() };
+        let result = train(&samples, &config, &mut rng);
+        assert!(!result.converged);
+        assert_eq!(result.epoch_errors.len(), 7);
+
+        // every epoch is within a tolerance of all the samples, so training stops after one
+        let tolerant = PerceptronConfig { tolerance: 1.0, ..config };
+        let result = train(&samples, &tolerant, &mut rng);
+        assert!(result.converged);
+        assert_eq!(result.epoch_errors.len(), 1);
+    }
+
+    #[test]
+    fn votes_count_every_step_and_averaging_still_separates() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = separable();
+        let voted = train(&samples, &PerceptronConfig { variant: Variant::Voted, ..PerceptronConfig::default() }, &mut rng);
+        let steps: usize = voted.votes.iter().map(|(_, c)| c).sum();
+        assert_eq!(steps, samples.len() * voted.epoch_errors.len());
+        assert_eq!(voted.classify(&samples), samples.iter().map(|s| s.class).collect::<Vec<i8>>());
+        assert!(voted.scores(&samples).iter().all(|v| (-1.0..=1.0).contains(v)));
+
+        let averaged = train(&samples, &PerceptronConfig { variant: Variant::Averaged, ..PerceptronConfig::default() }, &mut rng);
+        assert!(averaged.votes.is_empty());
+        assert_eq!(averaged.classify(&samples), samples.iter().map(|s| s.class).collect::<Vec<i8>>());
+    }
+
+    #[test]
+    fn an_rbf_kernel_perceptron_learns_xor() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let xor: Vec<Sample> = [(0.0, 0.0, 0), (0.0, 1.0, 1), (1.0, 0.0, 1), (1.0, 1.0, 0)].iter()
+            .map(|(x, y, class)| Sample { values: vec![1.0, *x, *y], class: *class })
+            .collect();
+        let config = PerceptronConfig { variant: Variant::Kernel, kernel: Kernel::Rbf { gamma: 2.0 }, rate: 1.0, ..PerceptronConfig::default() };
+        let result = train(&xor, &config, &mut rng);
+        assert!(result.converged);
+        assert!(result.weights.is_empty());
+        assert_eq!(result.classify(&xor), vec![0, 1, 1, 0]);
+
+        let linear = PerceptronConfig { kernel: Kernel::Linear, ..config };
+        assert!(!train(&xor, &linear, &mut rng).converged);
+    }
+
+    #[test]
+    fn multiclass_separates_three_clusters() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let centres = [(0.0, 4.0), (4.0, 0.0), (-4.0, -4.0)];
+        let samples: Vec<Sample> = centres.iter().enumerate()
+            .flat_map(|(c, (x, y))| [(0.5, 0.0), (-0.5, 0.5), (0.0, -0.5)].iter()
+                .map(move |(dx, dy)| Sample { values: vec![1.0, x + dx, y + dy], class: c as i8 }))
+            .collect();
+        let model = train_multiclass(&samples, 3, &PerceptronConfig::default(), &mut rng);
+        assert!(model.converged);
+        assert_eq!(model.weights.len(), 3);
+        assert_eq!(model.classify(&samples), samples.iter().map(|s| s.class).collect::<Vec<i8>>());
+    }
+}
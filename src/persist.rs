@@ -0,0 +1,674 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use rand::rngs::StdRng;
+use crate::Binning;
+use crate::calibration::{Calibration, Calibrator};
+use crate::features::{FeatureSet, Pipeline};
+use crate::forest::{self, Forest, Node};
+use crate::kernel::Kernel;
+use crate::lda::Lda;
+use crate::logistic::{Logistic, Penalty};
+use crate::mlp::{Activation, Layer, Mlp};
+use crate::naive_bayes::{self, NaiveBayes};
+use crate::neighbours::{Knn, Metric, ShrunkenCentroids};
+use crate::options::Options;
+use crate::perceptron::{self, KernelPerceptron, Sample};
+use crate::scaling::{Scaler, Scaling};
+use crate::selection::{Selection, Selector};
+use crate::svm::{self, Svm};
+
+
+/// Bumped whenever the layout below changes; older files are refused rather than misread.
+pub const VERSION: u32 = 11;
+const MAGIC: &str = "author_attribution_model";
+
+
+#[derive(Debug, Clone)]
+pub enum Model {
+    Perceptron(Vec<f64>),
+    /// A voted perceptron's weight vectors and their votes.
+    VotedPerceptron(Vec<(Vec<f64>, usize)>),
+    KernelPerceptron(KernelPerceptron),
+    Svm(Svm),
+    NaiveBayes(NaiveBayes),
+    Logistic(Logistic),
+    Knn(Knn),
+    ShrunkenCentroids(ShrunkenCentroids),
+    Tree(Node),
+    Forest(Forest),
+    Mlp(Mlp),
+    Lda(Lda)
+}
+
+impl Model {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::Perceptron(_) => "perceptron",
+            Model::VotedPerceptron(_) => "voted_perceptron",
+            Model::KernelPerceptron(_) => "kernel_perceptron",
+            Model::Svm(_) => "svm",
+            Model::NaiveBayes(_) => "naive_bayes",
+            Model::Logistic(_) => "logistic",
+            Model::Knn(_) => "knn",
+            Model::ShrunkenCentroids(_) => "shrunken_centroids",
+            Model::Tree(_) => "tree",
+            Model::Forest(_) => "random_forest",
+            Model::Mlp(_) => "mlp",
+            Model::Lda(_) => "lda"
+        }
+    }
+
+    /// With `verbose`, the perceptron's training history is printed.
+    pub fn train(name: &str, samples: &[Sample], options: &Options, rng: &mut StdRng, verbose: bool) -> Result<Model, String> {
+        match name {
+            "perceptron" => {
+                let result = perceptron::train(samples, &options.perceptron, rng);
+                if verbose {
+                    println!("{}", result);
+                }
+                if let Some(dual) = result.dual {
+                    Ok(Model::KernelPerceptron(dual))
+                } else if result.votes.is_empty() {
+                    Ok(Model::Perceptron(result.weights))
+                } else {
+                    Ok(Model::VotedPerceptron(result.votes))
+                }
+            },
+            "svm" => Ok(Model::Svm(svm::train(samples, 1.0, options.kernel))),
+            "naive_bayes" => {
+                naive_bayes::check_features(&options.features, options.scaling)?;
+                Ok(Model::NaiveBayes(NaiveBayes::fit(samples, options.alpha)?))
+            },
+            "logistic" => {
+                let model = Logistic::fit(samples, options.penalty);
+                if verbose {
+                    println!("{}", model);
+                }
+                Ok(Model::Logistic(model))
+            },
+            "knn" => Ok(Model::Knn(Knn::fit(samples, options.neighbours, options.metric))),
+            "shrunken_centroids" => Ok(Model::ShrunkenCentroids(ShrunkenCentroids::fit(samples, options.shrinkage)?)),
+            "tree" => {
+                let rows: Vec<usize> = (0..samples.len()).collect();
+                Ok(Model::Tree(forest::grow(samples, &rows, options.depth, samples[0].values.len(), rng)?))
+            },
+            "random_forest" => {
+                let (model, in_bag) = Forest::fit(samples, options.trees, options.depth, rng)?;
+                if verbose {
+                    println!("Random forest of {} trees, out-of-bag accuracy {:.3}", model.trees.len(), model.out_of_bag_accuracy(samples, &in_bag));
+                }
+                Ok(Model::Forest(model))
+            },
+            "mlp" => {
+                let model = Mlp::train(samples, &options.mlp, rng);
+                if verbose {
+                    println!("{}", model);
+                }
+                Ok(Model::Mlp(model))
+            },
+            "lda" => Ok(Model::Lda(Lda::fit(samples, options.ridge)?)),
+            other => Err(format!("Unknown model `{}` (expected perceptron, svm, naive_bayes, logistic, knn, shrunken_centroids, tree, random_forest, mlp or lda)", other))
+        }
+    }
+
+    /// Decision values; positive means class 1. For naive Bayes, logistic
+    /// regression and the MLP these are the log odds.
+    pub fn scores(&self, samples: &[Sample]) -> Vec<f64> {
+        match self {
+            Model::Perceptron(weights) => perceptron::scores(weights, samples),
+            Model::VotedPerceptron(votes) => samples.iter().map(|s| perceptron::vote(votes, s)).collect(),
+            Model::KernelPerceptron(model) => samples.iter().map(|s| model.decision(&s.values)).collect(),
+            Model::Svm(model) => samples.iter().map(|s| model.decision(&s.values)).collect(),
+            Model::NaiveBayes(model) => samples.iter().map(|s| model.log_odds(&s.values, 1)).collect(),
+            Model::Logistic(model) => samples.iter().map(|s| model.decision(&s.values)).collect(),
+            Model::Knn(model) => samples.iter().map(|s| model.score(&s.values)).collect(),
+            Model::ShrunkenCentroids(model) => samples.iter().map(|s| model.score(&s.values)).collect(),
+            Model::Tree(model) => samples.iter().map(|s| model.score(&s.values)).collect(),
+            Model::Forest(model) => samples.iter().map(|s| model.score(&s.values)).collect(),
+            Model::Mlp(model) => samples.iter().map(|s| model.log_odds(&s.values, 1)).collect(),
+            Model::Lda(model) => samples.iter().map(|s| model.score(&s.values)).collect()
+        }
+    }
+
+    pub fn classify(&self, samples: &[Sample]) -> Vec<i8> {
+        match self {
+            Model::Perceptron(weights) => perceptron::classify(weights.clone(), samples),
+            Model::VotedPerceptron(votes) => perceptron::classify_voted(votes, samples),
+            Model::KernelPerceptron(model) => model.classify(samples),
+            Model::Svm(model) => samples.iter().map(|s| (model.decision(&s.values) > 0.0) as i8).collect(),
+            Model::NaiveBayes(model) => samples.iter().map(|s| model.predict(&s.values)).collect(),
+            Model::Logistic(model) => samples.iter().map(|s| (model.decision(&s.values) > 0.0) as i8).collect(),
+            Model::Knn(model) => samples.iter().map(|s| model.predict(&s.values)).collect(),
+            Model::ShrunkenCentroids(model) => samples.iter().map(|s| model.predict(&s.values)).collect(),
+            Model::Tree(model) => samples.iter().map(|s| model.predict(&s.values)).collect(),
+            Model::Forest(model) => samples.iter().map(|s| model.predict(&s.values)).collect(),
+            Model::Mlp(model) => model.classify(samples),
+            Model::Lda(model) => model.classify(samples)
+        }
+    }
+}
+
+
+/// A trained model together with everything needed to apply it to a new book: the
+/// histogram binning, and the feature pipeline with its vocabularies, selection and
+/// scaling, plus the calibration of its decision values if there is one. None of it
+/// needs the training corpus again. The seed is kept so the training run can be
+/// reproduced.
+pub struct SavedModel {
+    pub seed: u64,
+    pub binning: Binning,
+    pub pipeline: Pipeline,
+    pub model: Model,
+    pub calibrator: Option<Calibrator>
+}
+
+
+/// Line-oriented text format: each line is a key followed by whitespace-separated
+/// values. Feature names and n-grams never contain whitespace, and floats are written
+/// with `Display`, which round-trips exactly.
+struct Writer {
+    out: String
+}
+
+impl Writer {
+    fn line<T: Display>(&mut self, key: &str, values: &[T]) {
+        self.out.push_str(key);
+        for v in values {
+            self.out.push(' ');
+            self.out.push_str(&v.to_string());
+        }
+        self.out.push('\n');
+    }
+}
+
+struct Reader<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>
+}
+
+impl<'a> Reader<'a> {
+    /// The values on the next line, which must start with `key`.
+    fn line(&mut self, key: &str) -> Result<Vec<&'a str>, String> {
+        let (n, line) = self.lines.next().ok_or_else(|| format!("Model file ended early; expected `{}`", key))?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some(k) if k == key => Ok(words.collect()),
+            _ => Err(format!("Line {} of model file: expected `{}`, found `{}`", n + 1, key, line))
+        }
+    }
+
+    /// The key and values on the next line, whatever the key.
+    fn any(&mut self) -> Result<(&'a str, Vec<&'a str>), String> {
+        let (_, line) = self.lines.next().ok_or_else(|| "Model file ended early".to_string())?;
+        let mut words = line.split_whitespace();
+        Ok((words.next().unwrap_or(""), words.collect()))
+    }
+
+    fn single(&mut self, key: &str) -> Result<&'a str, String> {
+        let values = self.line(key)?;
+        values.first().cloned().ok_or_else(|| format!("Model file: `{}` has no value", key))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, key: &str) -> Result<T, String> {
+        let value = self.single(key)?;
+        value.parse::<T>().map_err(|_| format!("Model file: bad value `{}` for `{}`", value, key))
+    }
+
+    fn parsed<T: std::str::FromStr>(&mut self, key: &str) -> Result<Vec<T>, String> {
+        self.line(key)?.iter()
+            .map(|v| v.parse::<T>().map_err(|_| format!("Model file: bad value `{}` for `{}`", v, key)))
+            .collect()
+    }
+}
+
+
+fn write_support(w: &mut Writer, support: &[Vec<f64>], coef: &[f64]) {
+    w.line("support_vectors", &[support.len()]);
+    for (coef, vector) in coef.iter().zip(support) {
+        w.line("coef", &[*coef]);
+        w.line("vector", vector);
+    }
+}
+
+fn read_support(r: &mut Reader) -> Result<(Vec<Vec<f64>>, Vec<f64>), String> {
+    let mut support = Vec::new();
+    let mut coef = Vec::new();
+    for _ in 0..r.number::<usize>("support_vectors")? {
+        coef.push(r.number("coef")?);
+        support.push(r.parsed("vector")?);
+    }
+    Ok((support, coef))
+}
+
+
+/// A tree in preorder: `split` lines are followed by their left then right subtrees.
+fn write_node(w: &mut Writer, node: &Node) {
+    match node {
+        Node::Leaf { class, share } => w.line("leaf", &[class.to_string(), share.to_string()]),
+        Node::Split { feature, threshold, left, right } => {
+            w.line("split", &[feature.to_string(), threshold.to_string()]);
+            write_node(w, left);
+            write_node(w, right);
+        }
+    }
+}
+
+fn read_node(r: &mut Reader) -> Result<Node, String> {
+    let bad = |line: &[&str]| format!("Model file: bad tree node `{}`", line.join(" "));
+    let (key, values) = r.any()?;
+    match (key, values.as_slice()) {
+        ("leaf", [class, share]) => Ok(Node::Leaf {
+            class: class.parse().map_err(|_| bad(&values))?,
+            share: share.parse().map_err(|_| bad(&values))?
+        }),
+        ("split", [feature, threshold]) => Ok(Node::Split {
+            feature: feature.parse().map_err(|_| bad(&values))?,
+            threshold: threshold.parse().map_err(|_| bad(&values))?,
+            left: Box::new(read_node(r)?),
+            right: Box::new(read_node(r)?)
+        }),
+        _ => Err(bad(&values))
+    }
+}
+
+
+pub fn save(path: &str, saved: &SavedModel) -> Result<(), String> {
+    let mut w = Writer { out: String::new() };
+    w.line(MAGIC, &[VERSION]);
+    w.line("model", &[saved.model.name()]);
+    w.line("seed", &[saved.seed]);
+
+    let binning = &saved.binning;
+    w.line("sentence_length_bins", &binning.sentence_length);
+    w.line("word_length_bins", &binning.word_length);
+    w.line("pronouns_per_sentence_bins", &binning.pronouns_per_sentence);
+    w.line("conjunctions_per_sentence_bins", &binning.conjunctions_per_sentence);
+    w.line("syllables_per_word_bins", &binning.syllables_per_word);
+
+    let pipeline = &saved.pipeline;
+    w.line("features", &pipeline.features.fields);
+    w.line("vocabularies", &[pipeline.features.vocabularies.len()]);
+    let mut vocabularies: Vec<(&String, &Vec<String>)> = pipeline.features.vocabularies.iter().collect();
+    vocabularies.sort();
+    for (field, vocabulary) in vocabularies {
+        w.line("vocabulary", &[field]);
+        w.line("grams", vocabulary);
+    }
+    w.line("selection", &[pipeline.selector.method]);
+    w.line("kept", &pipeline.selector.kept);
+    w.line("scaling", &[pipeline.scaler.method]);
+    w.line("shift", &pipeline.scaler.shift);
+    w.line("factor", &pipeline.scaler.factor);
+    let fixed: Vec<u8> = pipeline.scaler.fixed.iter().map(|f| *f as u8).collect();
+    w.line("fixed", &fixed);
+    w.line("names", &pipeline.names);
+
+    match &saved.model {
+        Model::Perceptron(weights) => w.line("weights", weights),
+        Model::VotedPerceptron(votes) => {
+            w.line("weight_vectors", &[votes.len()]);
+            for (weights, count) in votes {
+                w.line("votes", &[*count]);
+                w.line("weights", weights);
+            }
+        },
+        Model::KernelPerceptron(model) => {
+            w.line("kernel", &[model.kernel]);
+            write_support(&mut w, &model.support, &model.coef);
+        },
+        Model::Svm(model) => {
+            w.line("kernel", &[model.kernel]);
+            w.line("bias", &[model.bias]);
+            write_support(&mut w, &model.support, &model.coef);
+        },
+        Model::NaiveBayes(model) => {
+            w.line("classes", &model.classes);
+            let used: Vec<u8> = model.used.iter().map(|u| *u as u8).collect();
+            w.line("used", &used);
+            for (prior, likelihoods) in model.log_priors.iter().zip(&model.log_likelihoods) {
+                w.line("log_prior", &[*prior]);
+                w.line("log_likelihoods", likelihoods);
+            }
+        },
+        Model::Logistic(model) => {
+            w.line("penalty", &[model.penalty]);
+            w.line("iterations", &[model.iterations]);
+            w.line("converged", &[model.converged as u8]);
+            w.line("intercept", &[model.intercept]);
+            w.line("weights", &model.weights);
+        },
+        Model::Knn(model) => {
+            w.line("metric", &[model.metric]);
+            w.line("neighbours", &[model.k]);
+            w.line("mean", &model.mean);
+            w.line("sd", &model.sd);
+            w.line("samples", &[model.samples.len()]);
+            for s in &model.samples {
+                w.line("class", &[s.class]);
+                w.line("vector", &s.values);
+            }
+        },
+        Model::ShrunkenCentroids(model) => {
+            w.line("shrinkage", &[model.shrinkage]);
+            w.line("classes", &model.classes);
+            w.line("log_priors", &model.log_priors);
+            w.line("scale", &model.scale);
+            let used: Vec<u8> = model.used.iter().map(|u| *u as u8).collect();
+            w.line("used", &used);
+            for centroid in &model.centroids {
+                w.line("centroid", centroid);
+            }
+        },
+        Model::Tree(model) => write_node(&mut w, model),
+        Model::Forest(model) => {
+            w.line("trees", &[model.trees.len()]);
+            for tree in &model.trees {
+                write_node(&mut w, tree);
+            }
+        },
+        Model::Mlp(model) => {
+            w.line("activation", &[model.activation]);
+            w.line("classes", &model.classes);
+            w.line("layers", &[model.layers.len()]);
+            for layer in &model.layers {
+                w.line("layer", &[layer.weights.len()]);
+                w.line("bias", &layer.bias);
+                for row in &layer.weights {
+                    w.line("weights", row);
+                }
+            }
+        },
+        Model::Lda(model) => {
+            w.line("classes", &model.classes);
+            w.line("log_priors", &model.log_priors);
+            w.line("mean", &model.mean);
+            w.line("ratios", &model.ratios);
+            for axis in &model.axes {
+                w.line("axis", axis);
+            }
+            for centroid in &model.centroids {
+                w.line("centroid", centroid);
+            }
+        }
+    }
+
+    match &saved.calibrator {
+        None => w.line("calibration", &[Calibration::None]),
+        Some(Calibrator::Platt { a, b }) => {
+            w.line("calibration", &[Calibration::Platt]);
+            w.line("sigmoid", &[*a, *b]);
+        },
+        Some(Calibrator::Isotonic { edges, probabilities }) => {
+            w.line("calibration", &[Calibration::Isotonic]);
+            w.line("edges", edges);
+            w.line("probabilities", probabilities);
+        }
+    }
+
+    fs::write(path, w.out).map_err(|e| format!("Could not write model file {}: {}", path, e))
+}
+
+
+pub fn load(path: &str) -> Result<SavedModel, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read model file {}: {}", path, e))?;
+    let mut r = Reader { lines: text.lines().enumerate() };
+
+    let version = r.line(MAGIC).map_err(|_| format!("{} is not a model file", path))?;
+    if version != [VERSION.to_string()] {
+        return Err(format!("{} is a version {} model file, but this build reads version {}", path, version.join(" "), VERSION));
+    }
+    let kind = r.single("model")?;
+    let seed = r.number("seed")?;
+
+    let binning = Binning {
+        sentence_length: r.parsed("sentence_length_bins")?,
+        word_length: r.parsed("word_length_bins")?,
+        pronouns_per_sentence: r.parsed("pronouns_per_sentence_bins")?,
+        conjunctions_per_sentence: r.parsed("conjunctions_per_sentence_bins")?,
+        syllables_per_word: r.parsed("syllables_per_word_bins")?
+    };
+
+    let fields = r.line("features")?;
+    let mut features = FeatureSet::new(&fields)?;
+    let mut vocabularies = HashMap::new();
+    for _ in 0..r.number::<usize>("vocabularies")? {
+        let field = r.single("vocabulary")?.to_string();
+        let grams = r.line("grams")?.iter().map(|g| g.to_string()).collect();
+        vocabularies.insert(field, grams);
+    }
+    features.vocabularies = vocabularies;
+
+    let selector = Selector {
+        method: Selection::parse(r.single("selection")?)?,
+        kept: r.parsed("kept")?
+    };
+    let scaler = Scaler {
+        method: Scaling::parse(r.single("scaling")?)?,
+        shift: r.parsed("shift")?,
+        factor: r.parsed("factor")?,
+        fixed: r.parsed::<u8>("fixed")?.iter().map(|f| *f == 1).collect()
+    };
+    let names = r.line("names")?.iter().map(|n| n.to_string()).collect();
+    let pipeline = Pipeline { features, selector, scaler, names };
+
+    let model = match kind {
+        "perceptron" => Model::Perceptron(r.parsed("weights")?),
+        "voted_perceptron" => {
+            let mut votes = Vec::new();
+            for _ in 0..r.number::<usize>("weight_vectors")? {
+                let count = r.number("votes")?;
+                votes.push((r.parsed("weights")?, count));
+            }
+            Model::VotedPerceptron(votes)
+        },
+        "kernel_perceptron" => {
+            let kernel = Kernel::parse(r.single("kernel")?)?;
+            let (support, coef) = read_support(&mut r)?;
+            Model::KernelPerceptron(KernelPerceptron { kernel, support, coef })
+        },
+        "svm" => {
+            let kernel = Kernel::parse(r.single("kernel")?)?;
+            let bias = r.number("bias")?;
+            let (support, coef) = read_support(&mut r)?;
+            Model::Svm(Svm { kernel, support, coef, bias })
+        },
+        "naive_bayes" => {
+            let classes: Vec<i8> = r.parsed("classes")?;
+            let used = r.parsed::<u8>("used")?.iter().map(|u| *u == 1).collect();
+            let mut log_priors = Vec::new();
+            let mut log_likelihoods = Vec::new();
+            for _ in &classes {
+                log_priors.push(r.number("log_prior")?);
+                log_likelihoods.push(r.parsed("log_likelihoods")?);
+            }
+            Model::NaiveBayes(NaiveBayes { classes, log_priors, log_likelihoods, used })
+        },
+        "logistic" => Model::Logistic(Logistic {
+            penalty: Penalty::parse(r.single("penalty")?)?,
+            iterations: r.number("iterations")?,
+            converged: r.number::<u8>("converged")? == 1,
+            intercept: r.number("intercept")?,
+            weights: r.parsed("weights")?
+        }),
+        "knn" => {
+            let metric = Metric::parse(r.single("metric")?)?;
+            let k = r.number("neighbours")?;
+            let mean = r.parsed("mean")?;
+            let sd = r.parsed("sd")?;
+            let mut samples = Vec::new();
+            for _ in 0..r.number::<usize>("samples")? {
+                let class = r.number("class")?;
+                samples.push(Sample { values: r.parsed("vector")?, class });
+            }
+            Model::Knn(Knn { metric, k, samples, mean, sd })
+        },
+        "shrunken_centroids" => {
+            let shrinkage = r.number("shrinkage")?;
+            let classes: Vec<i8> = r.parsed("classes")?;
+            let log_priors = r.parsed("log_priors")?;
+            let scale = r.parsed("scale")?;
+            let used = r.parsed::<u8>("used")?.iter().map(|u| *u == 1).collect();
+            let mut centroids = Vec::new();
+            for _ in &classes {
+                centroids.push(r.parsed("centroid")?);
+            }
+            Model::ShrunkenCentroids(ShrunkenCentroids { shrinkage, classes, log_priors, centroids, scale, used })
+        },
+        "tree" => Model::Tree(read_node(&mut r)?),
+        "random_forest" => {
+            let mut trees = Vec::new();
+            for _ in 0..r.number::<usize>("trees")? {
+                trees.push(read_node(&mut r)?);
+            }
+            Model::Forest(Forest { trees })
+        },
+        "mlp" => {
+            let activation = Activation::parse(r.single("activation")?)?;
+            let classes = r.parsed("classes")?;
+            let mut layers = Vec::new();
+            for _ in 0..r.number::<usize>("layers")? {
+                let outputs: usize = r.number("layer")?;
+                let bias = r.parsed("bias")?;
+                let mut weights = Vec::new();
+                for _ in 0..outputs {
+                    weights.push(r.parsed("weights")?);
+                }
+                layers.push(Layer { weights, bias });
+            }
+            Model::Mlp(Mlp { activation, classes, layers, epoch_losses: Vec::new() })
+        },
+        "lda" => {
+            let classes: Vec<i8> = r.parsed("classes")?;
+            let log_priors = r.parsed("log_priors")?;
+            let mean = r.parsed("mean")?;
+            let ratios: Vec<f64> = r.parsed("ratios")?;
+            let mut axes = Vec::new();
+            for _ in &ratios {
+                axes.push(r.parsed("axis")?);
+            }
+            let mut centroids = Vec::new();
+            for _ in &classes {
+                centroids.push(r.parsed("centroid")?);
+            }
+            Model::Lda(Lda { classes, log_priors, mean, axes, ratios, centroids })
+        },
+        other => return Err(format!("{} holds an unknown model type `{}`", path, other))
+    };
+
+    let calibrator = match Calibration::parse(r.single("calibration")?)? {
+        Calibration::None => None,
+        Calibration::Platt => {
+            let sigmoid: Vec<f64> = r.parsed("sigmoid")?;
+            if sigmoid.len() != 2 {
+                return Err(format!("{}: `sigmoid` needs two values", path));
+            }
+            Some(Calibrator::Platt { a: sigmoid[0], b: sigmoid[1] })
+        },
+        Calibration::Isotonic => Some(Calibrator::Isotonic { edges: r.parsed("edges")?, probabilities: r.parsed("probabilities")? })
+    };
+
+    Ok(SavedModel { seed, binning, pipeline, model, calibrator })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::Binning;
+    use crate::calibration::Calibrator;
+    use crate::features::{self, FeatureSet, Pipeline};
+    use crate::kernel::Kernel;
+    use crate::options::Options;
+    use crate::perceptron::{Sample, Variant};
+    use crate::scaling::{Scaler, Scaling};
+    use crate::selection::{Selection, Selector};
+    use super::{load, save, Model, SavedModel};
+
+    /// Two overlapping clouds of non-negative values, so every model (naive Bayes
+    /// included) can be trained on them, the first value being the bias.
+    fn samples(rng: &mut StdRng) -> Vec<Sample> {
+        (0..40).map(|i| {
+            let class = (i % 2) as i8;
+            let mut values = vec![1.0];
+            values.extend((1..features::DEFAULT.len()).map(|j| rng.gen_range(0.0..4.0) + (class as f64) * j as f64 * 0.5));
+            Sample { values, class }
+        }).collect()
+    }
+
+    fn pipeline(samples: &[Sample]) -> Pipeline {
+        let names: Vec<String> = features::DEFAULT.iter().map(|n| n.to_string()).collect();
+        Pipeline {
+            features: FeatureSet::new(&features::DEFAULT).unwrap(),
+            selector: Selector::fit(Selection::All, &names, samples),
+            scaler: Scaler::fit(Scaling::None, &names, samples),
+            names
+        }
+    }
+
+    /// Trains `name`, saves and reloads it, and checks the reloaded model decides
+    /// every sample exactly as the original did.
+    fn round_trip(name: &str, options: &Options, expected_kind: &str) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples = samples(&mut rng);
+        let model = Model::train(name, &samples, options, &mut rng, false).unwrap();
+        assert_eq!(model.name(), expected_kind);
+
+        let path = std::env::temp_dir().join(format!("author_attribution_{}_{}.model", expected_kind, std::process::id()));
+        let path = path.to_str().unwrap();
+        let saved = SavedModel {
+            seed: 7,
+            binning: Binning::default(),
+            pipeline: pipeline(&samples),
+            model: model.clone(),
+            calibrator: Some(Calibrator::Platt { a: -1.5, b: 0.25 })
+        };
+        save(path, &saved).unwrap();
+        let loaded = load(path);
+        std::fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.seed, 7);
+        assert_eq!(loaded.model.name(), expected_kind);
+        assert_eq!(loaded.pipeline.names, saved.pipeline.names);
+        assert_eq!(loaded.model.scores(&samples), model.scores(&samples));
+        assert_eq!(loaded.model.classify(&samples), model.classify(&samples));
+        match loaded.calibrator {
+            Some(Calibrator::Platt { a, b }) => assert_eq!((a, b), (-1.5, 0.25)),
+            _ => panic!("calibrator not restored")
+        }
+    }
+
+    type Configure = fn(&mut Options);
+
+    /// Each model name, how to configure it, and the kind it should save as.
+    const CASES: &[(&str, Configure, &str)] = &[
+        ("perceptron", |_| {}, "perceptron"),
+        ("perceptron", |o| o.perceptron.variant = Variant::Voted, "voted_perceptron"),
+        ("perceptron", |o| {
+            o.perceptron.variant = Variant::Kernel;
+            o.perceptron.kernel = Kernel::Rbf { gamma: 0.1 };
+        }, "kernel_perceptron"),
+        ("svm", |o| o.kernel = Kernel::Polynomial { degree: 2, offset: 1.0 }, "svm"),
+        ("naive_bayes", |o| {
+            o.features = FeatureSet::new(&["word_counts"]).unwrap();
+            o.scaling = Scaling::None;
+        }, "naive_bayes"),
+        ("logistic", |_| {}, "logistic"),
+        ("knn", |_| {}, "knn"),
+        ("shrunken_centroids", |_| {}, "shrunken_centroids"),
+        ("tree", |_| {}, "tree"),
+        ("random_forest", |o| o.trees = 10, "random_forest"),
+        ("mlp", |o| o.mlp.epochs = 20, "mlp"),
+        ("lda", |_| {}, "lda")
+    ];
+
+    #[test]
+    fn every_model_kind_survives_a_round_trip() {
+        for (name, configure, expected_kind) in CASES {
+            let mut options = Options::parse(&["author_attribution".to_string()]);
+            configure(&mut options);
+            round_trip(name, &options, expected_kind);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use crate::perceptron::Sample;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scaling {
+    None,
+    /// Subtract the training mean and divide by the training standard deviation.
+    ZScore,
+    /// Map the training range of each feature onto [0, 1].
+    MinMax,
+    /// Scale each sample to unit Euclidean length.
+    L2,
+    /// Weight each feature by its smoothed inverse document frequency over the
+    /// training samples, then scale each sample to unit length.
+    TfIdf
+}
+
+impl Scaling {
+    pub fn parse(name: &str) -> Result<Scaling, String> {
+        match name {
+            "none" => Ok(Scaling::None),
+            "zscore" => Ok(Scaling::ZScore),
+            "minmax" => Ok(Scaling::MinMax),
+            "l2" => Ok(Scaling::L2),
+            "tfidf" => Ok(Scaling::TfIdf),
+            other => Err(format!("Unknown scaling `{}` (expected none, zscore, minmax, l2 or tfidf)", other))
+        }
+    }
+}
+
+/// The name accepted by `Scaling::parse`.
+impl fmt::Display for Scaling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Scaling::None => "none",
+            Scaling::ZScore => "zscore",
+            Scaling::MinMax => "minmax",
+            Scaling::L2 => "l2",
+            Scaling::TfIdf => "tfidf"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+/// A per-feature affine transform `(x - shift) * factor`, optionally followed by
+/// L2 normalisation. It is fitted on training samples only and then applied
+/// unchanged to anything else, so test and unknown documents never influence it.
+///
+/// Features named `bias` are passed through untouched, so a perceptron bias term
+/// survives scaling.
+#[derive(Debug, Clone)]
+pub struct Scaler {
+    pub method: Scaling,
+    pub shift: Vec<f64>,
+    pub factor: Vec<f64>,
+    pub fixed: Vec<bool>
+}
+
+impl Scaler {
+    pub fn fit(method: Scaling, names: &[String], samples: &[Sample]) -> Scaler {
+        let dims = names.len();
+        let n = samples.len() as f64;
+        let mut shift = vec![0.0; dims];
+        let mut factor = vec![1.0; dims];
+        let fixed: Vec<bool> = names.iter().map(|name| name == "bias").collect();
+
+        for j in (0..dims).filter(|j| !fixed[*j]) {
+            let column: Vec<f64> = samples.iter().map(|s| s.values[j]).collect();
+            match method {
+                Scaling::ZScore => {
+                    let mean = column.iter().sum::<f64>() / n;
+                    let variance = column.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+                    shift[j] = mean;
+                    // a feature with no spread in training can't be standardised, only centred
+                    if variance > 0.0 {
+                        factor[j] = 1.0 / variance.sqrt();
+                    }
+                },
+                Scaling::MinMax => {
+                    let min = column.iter().cloned().fold(f64::INFINITY, f64::min);
+                    let max = column.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                    shift[j] = min;
+                    if max > min {
+                        factor[j] = 1.0 / (max - min);
+                    }
+                },
+                Scaling::TfIdf => {
+                    let df = column.iter().filter(|x| **x > 0.0).count() as f64;
+                    factor[j] = ((1.0 + n) / (1.0 + df)).ln() + 1.0;
+                },
+                Scaling::None | Scaling::L2 => ()
+            }
+        }
+
+        Scaler { method, shift, factor, fixed }
+    }
+
+    pub fn transform(&self, values: &[f64]) -> Vec<f64> {
+        assert_eq!(values.len(), self.shift.len());
+        let mut out: Vec<f64> = values.iter().enumerate()
+            .map(|(j, x)| (x - self.shift[j]) * self.factor[j])
+            .collect();
+
+        if self.method == Scaling::L2 || self.method == Scaling::TfIdf {
+            let norm = out.iter().enumerate()
+                .filter(|(j, _)| !self.fixed[*j])
+                .map(|(_, x)| x * x)
+                .sum::<f64>()
+                .sqrt();
+            if norm > 0.0 {
+                for (j, x) in out.iter_mut().enumerate() {
+                    if !self.fixed[j] {
+                        *x /= norm;
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn transform_all(&self, samples: &[Sample]) -> Vec<Sample> {
+        samples.iter().map(|s| Sample { values: self.transform(&s.values), class: s.class }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fit(method: Scaling) -> Scaler {
+        let names: Vec<String> = ["bias", "a", "b"].iter().map(|n| n.to_string()).collect();
+        let samples = vec![
+            Sample { values: vec![1.0, 1.0, 0.0], class: 0 },
+            Sample { values: vec![1.0, 3.0, 0.0], class: 1 },
+            Sample { values: vec![1.0, 5.0, 2.0], class: 1 }
+        ];
+        Scaler::fit(method, &names, &samples)
+    }
+
+    #[test]
+    fn zscore_standardises_training_columns_and_leaves_the_bias() {
+        let scaler = fit(Scaling::ZScore);
+        let sd = (8.0f64 / 3.0).sqrt();
+        let out = scaler.transform(&[1.0, 5.0, 1.0]);
+        assert_eq!(out[0], 1.0);
+        assert!((out[1] - 2.0 / sd).abs() < 1e-12);
+        assert!(out[2] > 0.0);
+    }
+
+    #[test]
+    fn minmax_maps_the_training_range_to_zero_one() {
+        let scaler = fit(Scaling::MinMax);
+        assert_eq!(scaler.transform(&[1.0, 1.0, 0.0]), vec![1.0, 0.0, 0.0]);
+        assert_eq!(scaler.transform(&[1.0, 5.0, 2.0]), vec![1.0, 1.0, 1.0]);
+        // values outside the training range aren't clipped
+        assert_eq!(scaler.transform(&[1.0, 7.0, 2.0])[1], 1.5);
+    }
+
+    #[test]
+    fn l2_normalises_all_but_the_bias() {
+        let scaler = fit(Scaling::L2);
+        assert_eq!(scaler.transform(&[1.0, 3.0, 4.0]), vec![1.0, 0.6, 0.8]);
+        assert_eq!(scaler.transform(&[1.0, 0.0, 0.0]), vec![1.0, 0.0, 0.0]);
+    }
+}
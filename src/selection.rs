@@ -0,0 +1,268 @@
+use std::fmt;
+use crate::perceptron::Sample;
+use crate::scaling::{Scaler, Scaling};
+use crate::kernel::Kernel;
+use crate::svm;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    All,
+    /// Keep the N n-gram features with the highest mean training frequency (Burrows' MFW).
+    MostFrequent(usize),
+    /// Eder's culling: keep n-gram features present in at least this fraction of training books.
+    Culling(f64),
+    /// Keep the N features with the largest chi-squared statistic against the class.
+    ChiSquared(usize),
+    /// Keep the N features with the largest information gain about the class.
+    InformationGain(usize),
+    /// Keep the N features with the largest weights in a linear SVM on standardised features.
+    SvmWeight(usize)
+}
+
+impl Selection {
+    /// Parses `none`, `mfw:N`, `cull:F`, `chi2:N`, `ig:N` or `svm:N`.
+    pub fn parse(spec: &str) -> Result<Selection, String> {
+        if spec == "none" {
+            return Ok(Selection::All);
+        }
+        let usage = format!("Unknown feature selection `{}` (expected none, mfw:N, cull:F, chi2:N, ig:N or svm:N)", spec);
+        let (method, arg) = spec.split_once(':').ok_or_else(|| usage.clone())?;
+        let count = || arg.parse::<usize>().map_err(|_| usage.clone());
+        match method {
+            "mfw" => Ok(Selection::MostFrequent(count()?)),
+            "cull" => arg.parse::<f64>().map(Selection::Culling).map_err(|_| usage.clone()),
+            "chi2" => Ok(Selection::ChiSquared(count()?)),
+            "ig" => Ok(Selection::InformationGain(count()?)),
+            "svm" => Ok(Selection::SvmWeight(count()?)),
+            _ => Err(usage)
+        }
+    }
+}
+
+/// The spec accepted by `Selection::parse`.
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selection::All => write!(f, "none"),
+            Selection::MostFrequent(n) => write!(f, "mfw:{}", n),
+            Selection::Culling(fraction) => write!(f, "cull:{}", fraction),
+            Selection::ChiSquared(n) => write!(f, "chi2:{}", n),
+            Selection::InformationGain(n) => write!(f, "ig:{}", n),
+            Selection::SvmWeight(n) => write!(f, "svm:{}", n)
+        }
+    }
+}
+
+
+fn is_ngram(name: &str) -> bool {
+    name.starts_with("word:") || name.starts_with("char:") || name.starts_with("count:")
+}
+
+fn column(samples: &[Sample], j: usize) -> Vec<f64> {
+    samples.iter().map(|s| s.values[j]).collect()
+}
+
+/// Chi-squared and information gain need discrete values, so each feature is split at
+/// its training median into "high" and "low".
+fn above_median(samples: &[Sample], j: usize) -> Vec<bool> {
+    let mut values = column(samples, j);
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let median = values[values.len() / 2];
+    samples.iter().map(|s| s.values[j] > median).collect()
+}
+
+/// Counts of [low, high] x class.
+fn contingency(samples: &[Sample], j: usize, classes: &[i8]) -> [Vec<f64>; 2] {
+    let mut table = [vec![0.0; classes.len()], vec![0.0; classes.len()]];
+    for (s, high) in samples.iter().zip(above_median(samples, j)) {
+        let c = classes.iter().position(|c| *c == s.class).unwrap();
+        table[high as usize][c] += 1.0;
+    }
+    table
+}
+
+fn chi_squared(table: &[Vec<f64>; 2]) -> f64 {
+    let n: f64 = table.iter().flatten().sum();
+    let mut chi2 = 0.0;
+    for row in table {
+        let row_total: f64 = row.iter().sum();
+        for (c, observed) in row.iter().enumerate() {
+            let column_total = table[0][c] + table[1][c];
+            let expected = row_total * column_total / n;
+            if expected > 0.0 {
+                chi2 += (observed - expected).powi(2) / expected;
+            }
+        }
+    }
+    chi2
+}
+
+fn entropy(counts: &[f64]) -> f64 {
+    let n: f64 = counts.iter().sum();
+    counts.iter().filter(|c| **c > 0.0).map(|c| -(c / n) * (c / n).log2()).sum()
+}
+
+fn information_gain(table: &[Vec<f64>; 2]) -> f64 {
+    let n: f64 = table.iter().flatten().sum();
+    let class_totals: Vec<f64> = (0..table[0].len()).map(|c| table[0][c] + table[1][c]).collect();
+    let conditional: f64 = table.iter()
+        .map(|row| row.iter().sum::<f64>() / n * entropy(row))
+        .sum();
+    entropy(&class_totals) - conditional
+}
+
+/// Sum of absolute linear SVM weights over one-vs-rest problems for each class
+/// (a single problem when there are only two classes).
+fn svm_weights(samples: &[Sample], candidates: &[usize], classes: &[i8]) -> Vec<f64> {
+    let names: Vec<String> = candidates.iter().map(|j| j.to_string()).collect();
+    let reduced: Vec<Sample> = samples.iter()
+        .map(|s| Sample { values: candidates.iter().map(|j| s.values[*j]).collect(), class: s.class })
+        .collect();
+    let standardised = Scaler::fit(Scaling::ZScore, &names, &reduced).transform_all(&reduced);
+
+    let positives: &[i8] = if classes.len() == 2 { &classes[1..] } else { classes };
+    let mut scores = vec![0.0; candidates.len()];
+    for positive in positives {
+        let one_vs_rest: Vec<Sample> = standardised.iter()
+            .map(|s| Sample { values: s.values.clone(), class: (s.class == *positive) as i8 })
+            .collect();
+        let model = svm::train(&one_vs_rest, 1.0, Kernel::Linear);
+        for (score, w) in scores.iter_mut().zip(model.weights()) {
+            *score += w.abs();
+        }
+    }
+    scores
+}
+
+fn top(candidates: &[usize], scores: &[f64], n: usize) -> Vec<usize> {
+    let mut ranked: Vec<(usize, f64)> = candidates.iter().cloned().zip(scores.iter().cloned()).collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    ranked.into_iter().take(n).map(|(j, _)| j).collect()
+}
+
+
+/// The columns kept by a feature-selection method fitted on training samples. Kept
+/// columns stay in their original order, and a `bias` column is always kept.
+#[derive(Debug, Clone)]
+pub struct Selector {
+    pub method: Selection,
+    pub kept: Vec<usize>
+}
+
+impl Selector {
+    pub fn fit(method: Selection, names: &[String], samples: &[Sample]) -> Selector {
+        let n = samples.len() as f64;
+        let all: Vec<usize> = (0..names.len()).collect();
+        let bias: Vec<usize> = all.iter().cloned().filter(|j| names[*j] == "bias").collect();
+        let candidates: Vec<usize> = all.iter().cloned().filter(|j| names[*j] != "bias").collect();
+        // MFW and culling only make sense for frequencies; other features pass straight through
+        let ngrams: Vec<usize> = candidates.iter().cloned().filter(|j| is_ngram(&names[*j])).collect();
+        let others: Vec<usize> = candidates.iter().cloned().filter(|j| !is_ngram(&names[*j])).collect();
+
+        let mut classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+        classes.sort_unstable();
+        classes.dedup();
+
+        let mut kept = match method {
+            Selection::All => all.clone(),
+            Selection::MostFrequent(count) => {
+                let means: Vec<f64> = ngrams.iter().map(|j| column(samples, *j).iter().sum::<f64>() / n).collect();
+                [bias, others, top(&ngrams, &means, count)].concat()
+            },
+            Selection::Culling(fraction) => {
+                let present: Vec<usize> = ngrams.iter().cloned()
+                    .filter(|j| column(samples, *j).iter().filter(|x| **x > 0.0).count() as f64 >= fraction * n)
+                    .collect();
+                [bias, others, present].concat()
+            },
+            Selection::ChiSquared(count) => {
+                let scores: Vec<f64> = candidates.iter().map(|j| chi_squared(&contingency(samples, *j, &classes))).collect();
+                [bias, top(&candidates, &scores, count)].concat()
+            },
+            Selection::InformationGain(count) => {
+                let scores: Vec<f64> = candidates.iter().map(|j| information_gain(&contingency(samples, *j, &classes))).collect();
+                [bias, top(&candidates, &scores, count)].concat()
+            },
+            Selection::SvmWeight(count) => {
+                let scores = svm_weights(samples, &candidates, &classes);
+                [bias, top(&candidates, &scores, count)].concat()
+            }
+        };
+        kept.sort_unstable();
+
+        Selector { method, kept }
+    }
+
+    pub fn select_names(&self, names: &[String]) -> Vec<String> {
+        self.kept.iter().map(|j| names[*j].clone()).collect()
+    }
+
+    pub fn apply(&self, values: &[f64]) -> Vec<f64> {
+        self.kept.iter().map(|j| values[*j]).collect()
+    }
+
+    pub fn apply_all(&self, samples: &[Sample]) -> Vec<Sample> {
+        samples.iter().map(|s| Sample { values: self.apply(&s.values), class: s.class }).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Vec<String> {
+        ["bias", "length", "word:the", "word:of", "char:ab"].iter().map(|n| n.to_string()).collect()
+    }
+
+    /// `word:of` separates the classes at its median; the others don't.
+    fn samples() -> Vec<Sample> {
+        vec![
+            Sample { values: vec![1.0, 5.0, 10.0, 1.0, 0.0], class: 0 },
+            Sample { values: vec![1.0, 7.0, 12.0, 0.0, 1.0], class: 0 },
+            Sample { values: vec![1.0, 6.0, 11.0, 1.0, 0.0], class: 0 },
+            Sample { values: vec![1.0, 6.0, 2.0, 3.0, 1.0], class: 1 },
+            Sample { values: vec![1.0, 5.0, 3.0, 4.0, 0.0], class: 1 }
+        ]
+    }
+
+    fn kept(method: Selection) -> Vec<usize> {
+        Selector::fit(method, &names(), &samples()).kept
+    }
+
+    #[test]
+    fn specs_round_trip_through_display() {
+        for spec in &["none", "mfw:100", "cull:0.5", "chi2:20", "ig:20", "svm:20"] {
+            assert_eq!(Selection::parse(spec).unwrap().to_string(), *spec);
+        }
+        assert!(Selection::parse("mfw").is_err());
+        assert!(Selection::parse("chi2:x").is_err());
+    }
+
+    #[test]
+    fn a_perfect_split_scores_the_full_chi_squared_and_class_entropy() {
+        let table = [vec![3.0, 0.0], vec![0.0, 2.0]];
+        assert!((chi_squared(&table) - 5.0).abs() < 1e-12);
+        let class_entropy = -(0.6f64 * 0.6f64.log2() + 0.4 * 0.4f64.log2());
+        assert!((information_gain(&table) - class_entropy).abs() < 1e-12);
+
+        let independent = [vec![2.0, 2.0], vec![1.0, 1.0]];
+        assert!(chi_squared(&independent).abs() < 1e-12);
+        assert!(information_gain(&independent).abs() < 1e-12);
+    }
+
+    #[test]
+    fn chi_squared_and_information_gain_keep_the_separating_feature() {
+        assert_eq!(kept(Selection::ChiSquared(1)), vec![0, 3]);
+        assert_eq!(kept(Selection::InformationGain(1)), vec![0, 3]);
+    }
+
+    #[test]
+    fn mfw_and_culling_only_filter_ngrams() {
+        assert_eq!(kept(Selection::MostFrequent(1)), vec![0, 1, 2]);
+        assert_eq!(kept(Selection::Culling(0.5)), vec![0, 1, 2, 3]);
+        assert_eq!(kept(Selection::Culling(0.4)), vec![0, 1, 2, 3, 4]);
+        assert_eq!(kept(Selection::All), vec![0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+use std::fs;
+
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 480.0;
+const MARGIN: f64 = 60.0;
+
+/// Colours for series, by index.
+pub const PALETTE: [&str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b"];
+
+/// `n` distinct colours: the palette while it lasts, otherwise hues spaced evenly
+/// around the colour wheel.
+pub fn colours(n: usize) -> Vec<String> {
+    if n <= PALETTE.len() {
+        return PALETTE.iter().take(n).map(|c| c.to_string()).collect();
+    }
+    (0..n).map(|i| format!("hsl({:.0}, 65%, 45%)", 360.0 * i as f64 / n as f64)).collect()
+}
+
+
+/// A minimal SVG chart: axes with ticks over fixed data ranges, and lines, points and
+/// a legend drawn in data coordinates.
+pub struct Plot {
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    body: String,
+    legend: Vec<(String, String)>,
+    description: Option<String>
+}
+
+impl Plot {
+    pub fn new(title: &str, x_label: &str, y_label: &str, x_range: (f64, f64), y_range: (f64, f64)) -> Plot {
+        // a zero-width range would put everything at infinity
+        let widen = |(low, high): (f64, f64)| if high > low { (low, high) } else { (low - 0.5, high + 0.5) };
+        let mut plot = Plot { x_range: widen(x_range), y_range: widen(y_range), body: String::new(), legend: Vec::new(), description: None };
+        plot.axes(title, x_label, y_label);
+        plot
+    }
+
+    fn x(&self, x: f64) -> f64 {
+        MARGIN + (x - self.x_range.0) / (self.x_range.1 - self.x_range.0) * (WIDTH - 2.0 * MARGIN)
+    }
+
+    fn y(&self, y: f64) -> f64 {
+        HEIGHT - MARGIN - (y - self.y_range.0) / (self.y_range.1 - self.y_range.0) * (HEIGHT - 2.0 * MARGIN)
+    }
+
+    fn axes(&mut self, title: &str, x_label: &str, y_label: &str) {
+        let (left, right, top, bottom) = (MARGIN, WIDTH - MARGIN, MARGIN, HEIGHT - MARGIN);
+        let b = &mut self.body;
+        writeln!(b, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#, left, top, right - left, bottom - top).unwrap();
+        writeln!(b, r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{}</text>"#, WIDTH / 2.0, top / 2.0, escape(title)).unwrap();
+        writeln!(b, r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#, WIDTH / 2.0, HEIGHT - 15.0, escape(x_label)).unwrap();
+        writeln!(b, r#"<text x="15" y="{}" text-anchor="middle" transform="rotate(-90 15 {})">{}</text>"#, HEIGHT / 2.0, HEIGHT / 2.0, escape(y_label)).unwrap();
+
+        for i in 0..=5 {
+            let fraction = i as f64 / 5.0;
+            let x_value = self.x_range.0 + fraction * (self.x_range.1 - self.x_range.0);
+            let y_value = self.y_range.0 + fraction * (self.y_range.1 - self.y_range.0);
+            let (x, y) = (self.x(x_value), self.y(y_value));
+            let b = &mut self.body;
+            writeln!(b, r#"<line x1="{:.1}" y1="{}" x2="{:.1}" y2="{}" stroke="black"/>"#, x, bottom, x, bottom + 5.0).unwrap();
+            writeln!(b, r#"<text x="{:.1}" y="{}" text-anchor="middle" font-size="11">{}</text>"#, x, bottom + 18.0, tick(x_value)).unwrap();
+            writeln!(b, r#"<line x1="{}" y1="{:.1}" x2="{}" y2="{:.1}" stroke="black"/>"#, left - 5.0, y, left, y).unwrap();
+            writeln!(b, r#"<text x="{}" y="{:.1}" text-anchor="end" font-size="11">{}</text>"#, left - 8.0, y + 4.0, tick(y_value)).unwrap();
+        }
+    }
+
+    pub fn line(&mut self, points: &[(f64, f64)], colour: &str) {
+        let path: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", self.x(*x), self.y(*y))).collect();
+        writeln!(self.body, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#, path.join(" "), colour).unwrap();
+    }
+
+    /// A dot, with `title` shown on hovering and `label` written beside it.
+    pub fn point(&mut self, x: f64, y: f64, colour: &str, title: &str, label: Option<&str>) {
+        let (x, y) = (self.x(x), self.y(y));
+        writeln!(self.body, r#"<circle cx="{:.1}" cy="{:.1}" r="4" fill="{}"><title>{}</title></circle>"#, x, y, colour, escape(title)).unwrap();
+        if let Some(label) = label {
+            writeln!(self.body, r#"<text x="{:.1}" y="{:.1}" font-size="10" fill="{}">{}</text>"#, x + 6.0, y - 4.0, colour, escape(label)).unwrap();
+        }
+    }
+
+    pub fn legend(&mut self, label: &str, colour: &str) {
+        self.legend.push((label.to_string(), colour.to_string()));
+    }
+
+    /// Metadata written into the file's `<desc>`, such as how to reproduce the plot.
+    pub fn describe(&mut self, text: &str) {
+        self.description = Some(text.to_string());
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif">"#, WIDTH, HEIGHT).unwrap();
+        if let Some(description) = &self.description {
+            writeln!(out, "<desc>{}</desc>", escape(description)).unwrap();
+        }
+        writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+        out.push_str(&self.body);
+        for (i, (label, colour)) in self.legend.iter().enumerate() {
+            let y = MARGIN + 15.0 + 18.0 * i as f64;
+            writeln!(out, r#"<rect x="{}" y="{}" width="12" height="12" fill="{}"/>"#, WIDTH - MARGIN - 140.0, y - 10.0, colour).unwrap();
+            writeln!(out, r#"<text x="{}" y="{}" font-size="12">{}</text>"#, WIDTH - MARGIN - 122.0, y, escape(label)).unwrap();
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.render()).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+}
+
+
+fn tick(value: f64) -> String {
+    let rounded = format!("{:.2}", value);
+    rounded.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
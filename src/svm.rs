@@ -1,27 +1,258 @@
-pub fn train(_pos: Vec<Vec<f64>>, _neg: Vec<Vec<f64>>) -> i32 {
-    
+use crate::kernel::Kernel;
+use crate::perceptron::Sample;
 
-    // while the optimality conditions are violated:
 
-        // select q variables from the working set B. The remaining variables are fixed at their current value
-        // decompose problem and solve QP-subproblem: Optimize W(alpha) on B
-    
-    // terminate and return alhpa
+/// A trained two-class SVM. Only the support vectors (non-zero alphas) are kept, each
+/// with its coefficient `alpha * y`, where y is +1 for class 1 and -1 for class 0.
+#[derive(Debug, Clone)]
+pub struct Svm {
+    pub kernel: Kernel,
+    pub support: Vec<Vec<f64>>,
+    pub coef: Vec<f64>,
+    pub bias: f64
+}
+
+impl Svm {
+    /// Signed distance-like score; positive means class 1.
+    pub fn decision(&self, values: &[f64]) -> f64 {
+        self.support.iter().zip(&self.coef).map(|(s, c)| c * self.kernel.apply(s, values)).sum::<f64>() + self.bias
+    }
 
-    return 1;
+    /// For a one-class SVM: whether a sample lies inside the boundary. Support vectors
+    /// sit on the boundary, where the solver only gets the decision value to within
+    /// its tolerance, so that much slack is allowed.
+    pub fn inside(&self, values: &[f64]) -> bool {
+        self.decision(values) >= -ONE_CLASS_TOLERANCE
+    }
+
+    /// The primal weight vector, sum of `alpha * y * x` over the support vectors. Only
+    /// meaningful with the linear kernel.
+    pub fn weights(&self) -> Vec<f64> {
+        let dims = self.support.first().map(|s| s.len()).unwrap_or(0);
+        let mut w = vec![0.0; dims];
+        for (s, c) in self.support.iter().zip(&self.coef) {
+            for (wj, sj) in w.iter_mut().zip(s) {
+                *wj += c * sj;
+            }
+        }
+        w
+    }
 }
 
-fn norm(vin: &Vec<i16>) -> Vec<f64> {
-    let mut sum = 0;
-    for i in vin {
-        sum += i*i;
+
+/// Soft-margin SVM trained with Platt's sequential minimal optimisation, i.e. SVMlight's
+/// decomposition with a working set of two: while the KKT conditions are violated, pick
+/// a pair of alphas, solve the two-variable QP analytically, and leave the rest fixed.
+pub fn train(samples: &[Sample], c: f64, kernel: Kernel) -> Svm {
+    let n = samples.len();
+    let y: Vec<f64> = samples.iter().map(|s| if s.class == 1 { 1.0 } else { -1.0 }).collect();
+    let k = kernel.gram(samples);
+
+    let tol = 1e-3;
+    let max_passes = 200;
+
+    let mut alpha = vec![0.0; n];
+    let mut b = 0.0;
+    let error = |alpha: &[f64], b: f64, i: usize| -> f64 {
+        (0..n).map(|j| alpha[j] * y[j] * k[j][i]).sum::<f64>() + b - y[i]
+    };
+
+    // Solve the QP over alphas i and j only, returning whether anything moved.
+    let take_step = |alpha: &mut Vec<f64>, b: &mut f64, i: usize, j: usize| -> bool {
+        if i == j {
+            return false;
+        }
+        let (ei, ej) = (error(alpha, *b, i), error(alpha, *b, j));
+        let (ai_old, aj_old) = (alpha[i], alpha[j]);
+        let (low, high) = if y[i] != y[j] {
+            ((aj_old - ai_old).max(0.0), (c + aj_old - ai_old).min(c))
+        } else {
+            ((ai_old + aj_old - c).max(0.0), (ai_old + aj_old).min(c))
+        };
+        if low >= high {
+            return false;
+        }
+        let eta = 2.0 * k[i][j] - k[i][i] - k[j][j];
+        if eta >= 0.0 {
+            return false;
+        }
+
+        let aj = (aj_old - y[j] * (ei - ej) / eta).clamp(low, high);
+        if (aj - aj_old).abs() < 1e-5 {
+            return false;
+        }
+        let ai = ai_old + y[i] * y[j] * (aj_old - aj);
+
+        let b1 = *b - ei - y[i] * (ai - ai_old) * k[i][i] - y[j] * (aj - aj_old) * k[i][j];
+        let b2 = *b - ej - y[i] * (ai - ai_old) * k[i][j] - y[j] * (aj - aj_old) * k[j][j];
+        *b = if ai > 0.0 && ai < c {
+            b1
+        } else if aj > 0.0 && aj < c {
+            b2
+        } else {
+            (b1 + b2) / 2.0
+        };
+        alpha[i] = ai;
+        alpha[j] = aj;
+        true
+    };
+
+    // partner choice is deterministic, so one pass without changes means convergence
+    for _pass in 0..max_passes {
+        let mut changed = 0;
+        for i in 0..n {
+            let ei = error(&alpha, b, i);
+            let violates_kkt = (y[i] * ei < -tol && alpha[i] < c) || (y[i] * ei > tol && alpha[i] > 0.0);
+            if !violates_kkt {
+                continue;
+            }
+
+            // Platt's second-choice heuristic: the partner with the largest |Ei - Ej|
+            // makes the biggest step; fall back to the others in turn if it stalls.
+            let errors: Vec<f64> = (0..n).map(|j| error(&alpha, b, j)).collect();
+            let mut order: Vec<usize> = (0..n).filter(|j| *j != i).collect();
+            order.sort_by(|p, q| (ei - errors[*q]).abs().partial_cmp(&(ei - errors[*p]).abs()).unwrap());
+            if order.iter().any(|j| take_step(&mut alpha, &mut b, i, *j)) {
+                changed += 1;
+            }
+        }
+        if changed == 0 {
+            break;
+        }
+    }
+
+    let mut support = Vec::new();
+    let mut coef = Vec::new();
+    for i in 0..n {
+        if alpha[i] > 1e-8 {
+            support.push(samples[i].values.clone());
+            coef.push(alpha[i] * y[i]);
+        }
     }
 
-    let sum = (sum as f64).sqrt();
-    let mut vout = Vec::new();
-    for i in vin {
-        vout.push( *i as f64 / sum )
+    Svm { kernel, support, coef, bias: b }
+}
+
+
+const ONE_CLASS_TOLERANCE: f64 = 1e-3;
+
+/// Schölkopf et al.'s one-class SVM, for when there are only examples of one class. It
+/// finds a boundary around the training samples so that at most a fraction `nu` of
+/// them fall outside it; the decision value is positive inside. Trained with the same
+/// two-alpha steps as `train`, each time moving weight between the alpha whose sample
+/// is most inside the boundary and the one most outside, keeping the alphas' sum fixed.
+pub fn train_one_class(samples: &[Sample], nu: f64, kernel: Kernel) -> Svm {
+    let n = samples.len();
+    let k = kernel.gram(samples);
+    let tol = ONE_CLASS_TOLERANCE;
+    let max_iterations = 100 * n.max(100);
+
+    // alphas in [0, 1] summing to nu * n, started (as in LIBSVM) with the first ones at the bound
+    let total = nu * n as f64;
+    let mut alpha = vec![0.0; n];
+    for (i, a) in alpha.iter_mut().enumerate() {
+        *a = (total - i as f64).clamp(0.0, 1.0);
     }
+    let mut gradient: Vec<f64> = (0..n).map(|i| (0..n).map(|j| alpha[j] * k[i][j]).sum()).collect();
 
-    return vout
-}
\ No newline at end of file
+    for _iteration in 0..max_iterations {
+        // i can grow and has the smallest gradient, j can shrink and has the largest
+        let i = (0..n).filter(|i| alpha[*i] < 1.0).min_by(|p, q| gradient[*p].partial_cmp(&gradient[*q]).unwrap());
+        let j = (0..n).filter(|j| alpha[*j] > 0.0).max_by(|p, q| gradient[*p].partial_cmp(&gradient[*q]).unwrap());
+        let (i, j) = match (i, j) {
+            (Some(i), Some(j)) if gradient[j] - gradient[i] > tol => (i, j),
+            _ => break
+        };
+
+        let curvature = (k[i][i] + k[j][j] - 2.0 * k[i][j]).max(1e-12);
+        let step = ((gradient[j] - gradient[i]) / curvature).min(1.0 - alpha[i]).min(alpha[j]);
+        alpha[i] += step;
+        alpha[j] -= step;
+        for (l, g) in gradient.iter_mut().enumerate() {
+            *g += step * (k[l][i] - k[l][j]);
+        }
+    }
+
+    // rho is the gradient at any free alpha; average them for stability, or take the
+    // midpoint of the bounds if every alpha is at 0 or 1. With nu = 1 every alpha is at
+    // 1 and only the lower bound exists, so the boundary passes through the outermost sample.
+    let free: Vec<f64> = (0..n).filter(|i| alpha[*i] > 1e-8 && alpha[*i] < 1.0 - 1e-8).map(|i| gradient[i]).collect();
+    let rho = if free.is_empty() {
+        let upper = (0..n).filter(|i| alpha[*i] < 1.0).map(|i| gradient[i]).reduce(f64::min);
+        let lower = (0..n).filter(|i| alpha[*i] > 0.0).map(|i| gradient[i]).reduce(f64::max);
+        match (upper, lower) {
+            (Some(upper), Some(lower)) => (upper + lower) / 2.0,
+            (Some(bound), None) | (None, Some(bound)) => bound,
+            (None, None) => 0.0
+        }
+    } else {
+        free.iter().sum::<f64>() / free.len() as f64
+    };
+
+    let mut support = Vec::new();
+    let mut coef = Vec::new();
+    for i in 0..n {
+        if alpha[i] > 1e-8 {
+            support.push(samples[i].values.clone());
+            coef.push(alpha[i]);
+        }
+    }
+
+    Svm { kernel, support, coef, bias: -rho }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(points: &[(f64, i8)]) -> Vec<Sample> {
+        points.iter().map(|(x, class)| Sample { values: vec![*x], class: *class }).collect()
+    }
+
+    #[test]
+    fn smo_finds_the_maximum_margin_on_separable_data() {
+        let samples = line(&[(-3.0, 0), (-2.0, 0), (-1.0, 0), (1.0, 1), (2.0, 1), (3.0, 1)]);
+        let model = train(&samples, 100.0, Kernel::Linear);
+        // the margin is 1 either side of 0, touching -1 and 1
+        assert_eq!(model.support, vec![vec![-1.0], vec![1.0]]);
+        assert!((model.weights()[0] - 1.0).abs() < 1e-3);
+        assert!(model.bias.abs() < 1e-3);
+        for s in &samples {
+            let y = if s.class == 1 { 1.0 } else { -1.0 };
+            assert!(y * model.decision(&s.values) >= 1.0 - 1e-3);
+        }
+    }
+
+    #[test]
+    fn an_rbf_svm_separates_xor() {
+        let xor: Vec<Sample> = [(0.0, 0.0, 0), (0.0, 1.0, 1), (1.0, 0.0, 1), (1.0, 1.0, 0)].iter()
+            .map(|(x, y, class)| Sample { values: vec![*x, *y], class: *class })
+            .collect();
+        let model = train(&xor, 10.0, Kernel::Rbf { gamma: 2.0 });
+        for s in &xor {
+            assert_eq!(model.decision(&s.values) > 0.0, s.class == 1);
+        }
+    }
+
+    #[test]
+    fn one_class_with_nu_one_keeps_a_finite_boundary() {
+        let samples = line(&[(0.0, 1), (0.5, 1), (1.0, 1), (4.0, 1)]);
+        let model = train_one_class(&samples, 1.0, Kernel::Rbf { gamma: 1.0 });
+        assert_eq!(model.coef, vec![1.0; 4]);
+        assert!(model.bias.is_finite());
+        // every training sample is on or outside the boundary
+        assert!(samples.iter().all(|s| model.decision(&s.values) <= ONE_CLASS_TOLERANCE));
+        assert!(samples.iter().any(|s| model.inside(&s.values)));
+    }
+
+    #[test]
+    fn one_class_rejects_points_far_from_the_training_samples() {
+        let samples = line(&[(0.0, 1), (0.2, 1), (0.4, 1), (0.6, 1), (0.8, 1), (1.0, 1), (5.0, 1)]);
+        let model = train_one_class(&samples, 0.2, Kernel::Rbf { gamma: 1.0 });
+        assert!(model.inside(&[0.5]));
+        assert!(!model.inside(&[10.0]));
+        let outside = samples.iter().filter(|s| !model.inside(&s.values)).count();
+        assert!(outside as f64 <= 0.2 * samples.len() as f64);
+    }
+}
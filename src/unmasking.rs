@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use rand::rngs::StdRng;
+use crate::eval;
+use crate::kernel::Kernel;
+use crate::perceptron::Sample;
+use crate::scaling::{Scaler, Scaling};
+use crate::svm::{self, Svm};
+
+
+/// Word-frequency features: the most frequent words over both texts, as in Koppel &
+/// Schler's original setup.
+pub const VOCABULARY: usize = 250;
+/// Rounds of elimination, giving curves of `ROUNDS + 1` accuracies.
+pub const ROUNDS: usize = 10;
+/// The most positive and the most negative weights removed each round, this many each.
+const REMOVED_PER_ROUND: usize = 3;
+const FOLDS: usize = 10;
+/// Words per chunk; a text's last, incomplete chunk is dropped.
+pub const CHUNK_WORDS: usize = 500;
+/// Chunks drawn at random from each side of a comparison, which bounds the cost of
+/// the `(ROUNDS + 1) * (FOLDS + 1)` SVM fits per curve.
+pub const MAX_CHUNKS: usize = 25;
+
+
+/// The `size` words with the highest total relative frequency over all the chunks.
+pub fn vocabulary(chunks: &[&HashMap<String, f64>], size: usize) -> Vec<String> {
+    let mut totals: HashMap<&String, f64> = HashMap::new();
+    for chunk in chunks {
+        for (word, frequency) in chunk.iter() {
+            *totals.entry(word).or_insert(0.0) += frequency;
+        }
+    }
+    let mut ranked: Vec<(&String, f64)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(b.0)));
+    ranked.into_iter().take(size).map(|(w, _)| w.clone()).collect()
+}
+
+pub fn vector(chunk: &HashMap<String, f64>, vocabulary: &[String]) -> Vec<f64> {
+    vocabulary.iter().map(|w| chunk.get(w).cloned().unwrap_or(0.0)).collect()
+}
+
+
+/// The unmasking curve for two sets of chunk vectors: the cross-validated accuracy of
+/// a linear SVM telling them apart, recorded before each round in which the features
+/// it leans on hardest are removed. Texts by the same author differ in a few
+/// features only, so their curve falls away quickly; different authors' stays high.
+pub fn curve(a: &[Vec<f64>], b: &[Vec<f64>], rng: &mut StdRng) -> Vec<f64> {
+    let samples: Vec<Sample> = a.iter().map(|v| Sample { values: v.clone(), class: 1 })
+        .chain(b.iter().map(|v| Sample { values: v.clone(), class: 0 }))
+        .collect();
+    let classes: Vec<i8> = samples.iter().map(|s| s.class).collect();
+    let folds = eval::stratified_folds(&classes, FOLDS, rng);
+
+    let mut columns: Vec<usize> = (0..samples[0].values.len()).collect();
+    let mut accuracies = Vec::new();
+    for _round in 0..=ROUNDS {
+        let reduced: Vec<Sample> = samples.iter()
+            .map(|s| Sample { values: columns.iter().map(|j| s.values[*j]).collect(), class: s.class })
+            .collect();
+
+        let mut correct = 0;
+        for fold in folds.iter().filter(|f| !f.is_empty()) {
+            let training: Vec<Sample> = (0..reduced.len()).filter(|i| !fold.contains(i)).map(|i| reduced[i].clone()).collect();
+            let held_out: Vec<Sample> = fold.iter().map(|i| reduced[*i].clone()).collect();
+            let (scaler, model) = fit(&training);
+            correct += scaler.transform_all(&held_out).iter()
+                .filter(|s| (model.decision(&s.values) > 0.0) as i8 == s.class)
+                .count();
+        }
+        accuracies.push(correct as f64 / reduced.len() as f64);
+
+        let (_, model) = fit(&reduced);
+        let weights = model.weights();
+        let mut order: Vec<usize> = (0..columns.len()).collect();
+        order.sort_by(|p, q| weights[*q].partial_cmp(&weights[*p]).unwrap());
+        let removed: Vec<usize> = order.iter().take(REMOVED_PER_ROUND)
+            .chain(order.iter().rev().take(REMOVED_PER_ROUND))
+            .cloned()
+            .collect();
+        columns = (0..columns.len()).filter(|j| !removed.contains(j)).map(|j| columns[j]).collect();
+        if columns.is_empty() {
+            break;
+        }
+    }
+    accuracies
+}
+
+/// A linear SVM on standardised features, so the weights are comparable.
+fn fit(samples: &[Sample]) -> (Scaler, Svm) {
+    let names: Vec<String> = (0..samples[0].values.len()).map(|j| j.to_string()).collect();
+    let scaler = Scaler::fit(Scaling::ZScore, &names, samples);
+    let model = svm::train(&scaler.transform_all(samples), 1.0, Kernel::Linear);
+    (scaler, model)
+}
+
+
+/// Features of a curve for the meta-classifier: the accuracies themselves, the drop
+/// over each round, and the largest single drop, after Koppel & Schler.
+pub fn curve_features(curve: &[f64]) -> Vec<f64> {
+    let drops: Vec<f64> = curve.windows(2).map(|w| w[0] - w[1]).collect();
+    let largest = drops.iter().cloned().fold(0.0, f64::max);
+    // curves cut short by running out of features are padded with their last value
+    let mut features = curve.to_vec();
+    features.resize(ROUNDS + 1, *curve.last().unwrap_or(&0.0));
+    features.extend(&drops);
+    features.resize(2 * ROUNDS + 1, 0.0);
+    features.push(largest);
+    features
+}
+
+
+/// Linear SVM over `curve_features`, trained on curves known to come from the same
+/// author (class 1) or from different authors (class 0).
+pub struct MetaClassifier {
+    scaler: Scaler,
+    model: Svm
+}
+
+impl MetaClassifier {
+    pub fn fit(same: &[Vec<f64>], different: &[Vec<f64>]) -> MetaClassifier {
+        let samples: Vec<Sample> = same.iter().map(|c| Sample { values: curve_features(c), class: 1 })
+            .chain(different.iter().map(|c| Sample { values: curve_features(c), class: 0 }))
+            .collect();
+        let names: Vec<String> = (0..samples[0].values.len()).map(|j| j.to_string()).collect();
+        let scaler = Scaler::fit(Scaling::ZScore, &names, &samples);
+        let model = svm::train(&scaler.transform_all(&samples), 1.0, Kernel::Linear);
+        MetaClassifier { scaler, model }
+    }
+
+    /// Positive means the curve looks like a same-author one.
+    pub fn decision(&self, curve: &[f64]) -> f64 {
+        self.model.decision(&self.scaler.transform(&curve_features(curve)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(words: &[(&str, f64)]) -> HashMap<String, f64> {
+        words.iter().map(|(w, f)| (w.to_string(), *f)).collect()
+    }
+
+    #[test]
+    fn vocabulary_ranks_by_total_frequency_then_alphabetically() {
+        let (a, b) = (chunk(&[("the", 0.5), ("of", 0.2), ("and", 0.1)]), chunk(&[("the", 0.4), ("and", 0.1), ("sea", 0.3)]));
+        let words = vocabulary(&[&a, &b], 3);
+        assert_eq!(words, vec!["the", "sea", "and"]);
+        assert_eq!(vector(&a, &words), vec![0.5, 0.0, 0.1]);
+    }
+
+    #[test]
+    fn curve_features_pad_short_curves_and_record_the_largest_drop() {
+        let features = curve_features(&[1.0, 0.9, 0.6]);
+        assert_eq!(features.len(), 2 * ROUNDS + 2);
+        assert_eq!(&features[..4], &[1.0, 0.9, 0.6, 0.6]);
+        assert!((features[ROUNDS + 1] - 0.1).abs() < 1e-12);
+        assert!((features[ROUNDS + 2] - 0.3).abs() < 1e-12);
+        assert_eq!(features[ROUNDS + 3], 0.0);
+        assert!((features[2 * ROUNDS + 1] - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn meta_classifier_tells_falling_curves_from_flat_ones() {
+        let falling = |start: f64| -> Vec<f64> { (0..=ROUNDS).map(|r| start - 0.05 * r as f64).collect() };
+        let flat = |level: f64| -> Vec<f64> { vec![level; ROUNDS + 1] };
+        let meta = MetaClassifier::fit(&[falling(1.0), falling(0.95), falling(0.9)], &[flat(1.0), flat(0.95), flat(0.9)]);
+        assert!(meta.decision(&falling(0.97)) > 0.0);
+        assert!(meta.decision(&flat(0.97)) < 0.0);
+    }
+}